@@ -2,7 +2,6 @@ use crate::commands::Nur;
 use crate::errors::{NurError, NurResult};
 use crate::names::NUR_NAME;
 use nu_engine::{get_full_help, CallExt};
-use nu_parser::escape_for_script_arg;
 use nu_parser::parse;
 use nu_protocol::ast::Expression;
 use nu_protocol::{
@@ -12,7 +11,6 @@ use nu_protocol::{
 };
 use nu_protocol::{report_parse_error, Spanned};
 use nu_utils::escape_quote_string;
-use nu_utils::stdout_write_all_and_flush;
 
 pub(crate) fn is_safe_taskname(name: &str) -> bool {
     // This is basically similar to string_should_be_quoted
@@ -59,7 +57,9 @@ pub(crate) fn gather_commandline_args(
 
         let flag_value = match arg.as_ref() {
             // "--some-file" => args.next().map(|a| escape_quote_string(&a)),
-            "--commands" | "-c" => args_iter.next().map(|a| escape_quote_string(a)),
+            "--commands" | "-c" | "--completions" | "--from" | "--report" | "--events" => {
+                args_iter.next().map(|a| escape_quote_string(a))
+            }
             _ => None,
         };
 
@@ -71,10 +71,10 @@ pub(crate) fn gather_commandline_args(
     }
 
     if has_task_call {
-        // Consume remaining elements in iterator
+        // Consume remaining elements in iterator, those are escaped by NurState when needed
         #[allow(clippy::while_let_on_iterator)]
         while let Some(arg) = args_iter.next() {
-            task_call.push(escape_for_script_arg(arg));
+            task_call.push(arg.clone());
         }
     } else {
         // Also remove "nur" from task_call
@@ -87,7 +87,7 @@ pub(crate) fn gather_commandline_args(
 pub(crate) fn parse_commandline_args(
     commandline_args: &str,
     engine_state: &mut EngineState,
-) -> Result<NurArgs, ShellError> {
+) -> NurResult<NurArgs> {
     let (block, delta) = {
         let mut working_set = StateWorkingSet::new(engine_state);
 
@@ -95,7 +95,7 @@ pub(crate) fn parse_commandline_args(
         if let Some(err) = working_set.parse_errors.first() {
             report_parse_error(&working_set, err);
 
-            std::process::exit(1);
+            return Err(NurError::ParseErrors(working_set.parse_errors));
         }
 
         (output, working_set.render())
@@ -113,20 +113,32 @@ pub(crate) fn parse_commandline_args(
             let quiet_execution = call.has_flag(engine_state, &mut stack, "quiet")?;
             let attach_stdin = call.has_flag(engine_state, &mut stack, "stdin")?;
             let show_help = call.has_flag(engine_state, &mut stack, "help")?;
+            let assume_yes = call.has_flag(engine_state, &mut stack, "yes")?;
+            let run_here = call.has_flag(engine_state, &mut stack, "here")?;
+            let use_nu_config = call.has_flag(engine_state, &mut stack, "use-nu-config")?;
+            let ci_mode = call.has_flag(engine_state, &mut stack, "ci")?;
+            let no_ci = call.has_flag(engine_state, &mut stack, "no-ci")?;
             let run_commands = call.get_flag_expr("commands");
+            let completions = call.get_flag_expr("completions");
+            let check_project = call.has_flag(engine_state, &mut stack, "check")?;
+            let format_files = call.has_flag(engine_state, &mut stack, "fmt")?;
+            let run_again = call.has_flag(engine_state, &mut stack, "again")?;
+            let show_history = call.has_flag(engine_state, &mut stack, "history")?;
+            let failed_only = call.has_flag(engine_state, &mut stack, "failed")?;
+            let init_project = call.has_flag(engine_state, &mut stack, "init")?;
+            let init_from = call.get_flag_expr("from");
+            let report = call.get_flag_expr("report");
+            let events = call.get_flag_expr("events");
+            let log_output = call.has_flag(engine_state, &mut stack, "log")?;
+            let force = call.has_flag(engine_state, &mut stack, "force")?;
+            let profile_startup = call.has_flag(engine_state, &mut stack, "profile-startup")?;
+            #[cfg(feature = "lsp")]
+            let run_lsp = call.has_flag(engine_state, &mut stack, "lsp")?;
             let enter_shell = call.has_flag(engine_state, &mut stack, "enter-shell")?;
+            let show_version = call.has_flag(engine_state, &mut stack, "version")?;
             #[cfg(feature = "debug")]
             let debug_output = call.has_flag(engine_state, &mut stack, "debug")?;
 
-            if call.has_flag(engine_state, &mut stack, "version")? {
-                let version = env!("CARGO_PKG_VERSION").to_string();
-                let _ = std::panic::catch_unwind(move || {
-                    stdout_write_all_and_flush(format!("{version}\n"))
-                });
-
-                std::process::exit(0);
-            }
-
             fn extract_contents(
                 expression: Option<&Expression>,
             ) -> Result<Option<Spanned<String>>, ShellError> {
@@ -149,39 +161,89 @@ pub(crate) fn parse_commandline_args(
             }
 
             let run_commands = extract_contents(run_commands)?;
+            let completions = extract_contents(completions)?;
+            let init_from = extract_contents(init_from)?;
+            let report = extract_contents(report)?;
+            let events = extract_contents(events)?;
 
             return Ok(NurArgs {
                 list_tasks,
                 quiet_execution,
                 attach_stdin,
                 show_help,
+                assume_yes,
+                run_here,
+                use_nu_config,
+                ci_mode,
+                no_ci,
                 run_commands,
+                completions,
+                check_project,
+                format_files,
+                run_again,
+                show_history,
+                failed_only,
+                init_project,
+                init_from,
+                report,
+                events,
+                log_output,
+                force,
+                profile_startup,
+                #[cfg(feature = "lsp")]
+                run_lsp,
                 enter_shell,
+                show_version,
                 #[cfg(feature = "debug")]
                 debug_output,
             });
         }
     }
 
-    // Just give the help and exit if the above fails
-    let full_help = get_full_help(&Nur, engine_state, &mut stack);
+    // Just give the help if the above fails
+    let full_help = get_full_help(&Nur::default(), engine_state, &mut stack);
     print!("{full_help}");
-    std::process::exit(1);
+    Err(NurError::InitError(String::from(
+        "Could not parse the nur arguments",
+    )))
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct NurArgs {
-    pub(crate) list_tasks: bool,
-    pub(crate) quiet_execution: bool,
-    pub(crate) attach_stdin: bool,
-    pub(crate) show_help: bool,
-    pub(crate) run_commands: Option<Spanned<String>>,
-    pub(crate) enter_shell: bool,
+    pub list_tasks: bool,
+    pub quiet_execution: bool,
+    pub attach_stdin: bool,
+    pub show_help: bool,
+    pub assume_yes: bool,
+    pub run_here: bool,
+    pub use_nu_config: bool,
+    pub ci_mode: bool,
+    pub no_ci: bool,
+    pub run_commands: Option<Spanned<String>>,
+    pub completions: Option<Spanned<String>>,
+    pub check_project: bool,
+    pub format_files: bool,
+    #[allow(dead_code)] // --again is handled before the engine is set up, see wants_replay
+    pub run_again: bool,
+    pub show_history: bool,
+    pub failed_only: bool,
+    pub init_project: bool,
+    pub init_from: Option<Spanned<String>>,
+    pub report: Option<Spanned<String>>,
+    pub events: Option<Spanned<String>>,
+    pub log_output: bool,
+    pub force: bool,
+    pub profile_startup: bool,
+    #[cfg(feature = "lsp")]
+    pub run_lsp: bool,
+    pub enter_shell: bool,
+    pub show_version: bool,
     #[cfg(feature = "debug")]
-    pub(crate) debug_output: bool,
+    pub debug_output: bool,
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::let_and_return)]
 mod tests {
     use super::*;
     use crate::engine::init_engine_state;
@@ -210,6 +272,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_gather_commandline_args_keeps_raw_task_args() {
+        let args = vec![
+            String::from("nur"),
+            String::from("some_task_name"),
+            String::from("a b"),
+        ];
+        let (_, _, task_call) = gather_commandline_args(args).unwrap();
+        assert_eq!(
+            task_call,
+            vec![
+                String::from("nur"),
+                String::from("some_task_name"),
+                String::from("a b")
+            ]
+        );
+    }
+
     #[test]
     fn test_gather_commandline_args_handles_missing_nur_args() {
         let args = vec![
@@ -283,8 +363,29 @@ mod tests {
         assert_eq!(nur_args.quiet_execution, false);
         assert_eq!(nur_args.attach_stdin, false);
         assert_eq!(nur_args.show_help, false);
+        assert_eq!(nur_args.assume_yes, false);
+        assert_eq!(nur_args.run_here, false);
+        assert_eq!(nur_args.use_nu_config, false);
+        assert_eq!(nur_args.ci_mode, false);
+        assert_eq!(nur_args.no_ci, false);
         assert!(nur_args.run_commands.is_none());
+        assert!(nur_args.completions.is_none());
+        assert_eq!(nur_args.check_project, false);
+        assert_eq!(nur_args.format_files, false);
+        assert_eq!(nur_args.run_again, false);
+        assert_eq!(nur_args.show_history, false);
+        assert_eq!(nur_args.failed_only, false);
+        assert_eq!(nur_args.init_project, false);
+        assert!(nur_args.init_from.is_none());
+        assert!(nur_args.report.is_none());
+        assert!(nur_args.events.is_none());
+        assert_eq!(nur_args.log_output, false);
+        assert_eq!(nur_args.force, false);
+        assert_eq!(nur_args.profile_startup, false);
+        #[cfg(feature = "lsp")]
+        assert_eq!(nur_args.run_lsp, false);
         assert_eq!(nur_args.enter_shell, false);
+        assert_eq!(nur_args.show_version, false);
     }
 
     #[test]
@@ -329,6 +430,147 @@ mod tests {
         assert_eq!(nur_args.run_commands.unwrap().item, "some_command");
     }
 
+    #[test]
+    fn test_parse_commandline_args_completions() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args =
+            parse_commandline_args("nur --completions 'fish'", &mut engine_state).unwrap();
+        assert!(nur_args.completions.is_some());
+        assert_eq!(nur_args.completions.unwrap().item, "fish");
+    }
+
+    #[test]
+    fn test_parse_commandline_args_check() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --check", &mut engine_state).unwrap();
+        assert!(nur_args.check_project);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_init() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args =
+            parse_commandline_args("nur --init --from 'make' --force", &mut engine_state).unwrap();
+        assert!(nur_args.init_project);
+        assert_eq!(nur_args.init_from.unwrap().item, "make");
+        assert!(nur_args.force);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_report() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args =
+            parse_commandline_args("nur --report 'junit=report.xml'", &mut engine_state).unwrap();
+        assert_eq!(nur_args.report.unwrap().item, "junit=report.xml");
+    }
+
+    #[test]
+    fn test_parse_commandline_args_events() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --events 'jsonl'", &mut engine_state).unwrap();
+        assert_eq!(nur_args.events.unwrap().item, "jsonl");
+    }
+
+    #[test]
+    fn test_parse_commandline_args_here() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --here", &mut engine_state).unwrap();
+        assert!(nur_args.run_here);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_use_nu_config() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --use-nu-config", &mut engine_state).unwrap();
+        assert!(nur_args.use_nu_config);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_profile_startup() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --profile-startup", &mut engine_state).unwrap();
+        assert!(nur_args.profile_startup);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_log() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --log", &mut engine_state).unwrap();
+        assert!(nur_args.log_output);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_fmt() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --fmt --check", &mut engine_state).unwrap();
+        assert!(nur_args.format_files);
+        assert!(nur_args.check_project);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_yes() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --yes", &mut engine_state).unwrap();
+        assert!(nur_args.assume_yes);
+
+        let nur_args = parse_commandline_args("nur -y", &mut engine_state).unwrap();
+        assert!(nur_args.assume_yes);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_ci() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --ci", &mut engine_state).unwrap();
+        assert!(nur_args.ci_mode);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_no_ci() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --no-ci", &mut engine_state).unwrap();
+        assert!(nur_args.no_ci);
+        assert!(!nur_args.ci_mode);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_again() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --again", &mut engine_state).unwrap();
+        assert!(nur_args.run_again);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_history() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --history --failed", &mut engine_state).unwrap();
+        assert!(nur_args.show_history);
+        assert!(nur_args.failed_only);
+    }
+
+    #[test]
+    #[cfg(feature = "lsp")]
+    fn test_parse_commandline_args_lsp() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --lsp", &mut engine_state).unwrap();
+        assert!(nur_args.run_lsp);
+    }
+
     #[test]
     fn test_parse_commandline_args_enter_shell() {
         let mut engine_state = _create_minimal_engine_for_erg_parsing();
@@ -336,4 +578,22 @@ mod tests {
         let nur_args = parse_commandline_args("nur --enter-shell", &mut engine_state).unwrap();
         assert_eq!(nur_args.enter_shell, true);
     }
+
+    #[test]
+    fn test_parse_commandline_args_version() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        let nur_args = parse_commandline_args("nur --version", &mut engine_state).unwrap();
+        assert!(nur_args.show_version);
+    }
+
+    #[test]
+    fn test_parse_commandline_args_returns_parse_errors() {
+        let mut engine_state = _create_minimal_engine_for_erg_parsing();
+
+        assert!(matches!(
+            parse_commandline_args("nur --unknown-flag", &mut engine_state),
+            Err(NurError::ParseErrors(_))
+        ));
+    }
 }
@@ -0,0 +1,126 @@
+use crate::engine::{init_engine_state, NurEngine};
+use crate::errors::{NurError, NurResult};
+use crate::names::NUR_NAME;
+use crate::path::current_dir_from_environment;
+use crate::state::NurState;
+use std::path::PathBuf;
+
+/// Builder to set up a fully loaded [`NurEngine`] for embedding nur into other tools
+pub struct NurBuilder {
+    run_path: PathBuf,
+    args: Vec<String>,
+    load_nurfiles: bool,
+}
+
+impl Default for NurBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NurBuilder {
+    pub fn new() -> Self {
+        NurBuilder {
+            run_path: current_dir_from_environment(),
+            args: vec![String::from(NUR_NAME)],
+            load_nurfiles: true,
+        }
+    }
+
+    /// Path to start the project discovery from (defaults to the current directory)
+    pub fn run_path<P: Into<PathBuf>>(mut self, run_path: P) -> Self {
+        self.run_path = run_path.into();
+        self
+    }
+
+    /// Full command line as passed to the nur binary, including "nur" itself
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether to load secrets, env.nu, config.nu and the nurfiles when building (defaults to true)
+    pub fn load_nurfiles(mut self, load_nurfiles: bool) -> Self {
+        self.load_nurfiles = load_nurfiles;
+        self
+    }
+
+    pub fn build(self) -> NurResult<NurEngine> {
+        let nur_state = NurState::new(self.run_path, self.args)?;
+        if !nur_state.has_project_path {
+            return Err(NurError::NurfileNotFound());
+        }
+
+        let engine_state = init_engine_state(&nur_state.project_path)?;
+        let mut nur_engine = NurEngine::new(engine_state, nur_state)?;
+
+        if self.load_nurfiles {
+            nur_engine.load_secrets()?;
+            nur_engine.load_env()?;
+            nur_engine.load_config()?;
+            nur_engine.load_nurfiles()?;
+        }
+
+        Ok(nur_engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::names::NUR_FILE;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_nur_builder_requires_project_path() {
+        let temp_dir = tempdir().unwrap();
+
+        let result = NurBuilder::new().run_path(temp_dir.path()).build();
+
+        assert!(matches!(result, Err(NurError::NurfileNotFound())));
+    }
+
+    #[test]
+    fn test_nur_builder_will_load_nurfiles() {
+        let temp_dir = tempdir().unwrap();
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile
+            .write_all(b"def \"nur some-task\" [] { \"some-output\" }")
+            .unwrap();
+
+        let mut nur_engine = NurBuilder::new().run_path(temp_dir.path()).build().unwrap();
+
+        assert_eq!(nur_engine.list_tasks(), vec![String::from("some-task")]);
+        let output = nur_engine.run_task(["some-task"]).unwrap();
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(output.to_string_lossy(), "some-output");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_nur_builder_task_output_is_captured() {
+        let temp_dir = tempdir().unwrap();
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile
+            .write_all(
+                b"def \"nur some-task\" [] {\n    print \"printed\"\n    print -e \"some error\"\n    ^sh -c \"echo external\"\n    \"some-output\"\n}",
+            )
+            .unwrap();
+
+        let mut nur_engine = NurBuilder::new().run_path(temp_dir.path()).build().unwrap();
+
+        // Tests running in parallel may print while capturing
+        let output = nur_engine.run_task(["some-task"]).unwrap();
+        assert!(output.stdout.contains("printed\nexternal\n"));
+        assert!(output.stderr.contains("some error\n"));
+        assert_eq!(output.to_string_lossy(), "some-output");
+    }
+}
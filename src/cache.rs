@@ -0,0 +1,314 @@
+use crate::engine::NurEngine;
+use crate::errors::NurResult;
+use crate::state::NurState;
+use nu_protocol::Signature;
+use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Signatures of all tasks, cached in .nur/cache/ to answer completions and `--list` without
+/// setting up the engine and loading all nurfiles
+#[derive(Clone, Debug)]
+pub(crate) struct TaskCache {
+    tasks: Vec<(String, Signature)>,
+    // Other files loaded by `source` or `use`, also part of the cache key
+    sources: Vec<PathBuf>,
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// Hash of all files which may define tasks (including the given sources), and the nur version
+///
+/// The nu env.nu and config.nu are always included, as the cache is also used when they are not
+/// loaded (without --use-nu-config).
+fn cache_key(nur_state: &NurState, sources: &[PathBuf]) -> String {
+    let mut files = vec![
+        nur_state.nurfile_path.clone(),
+        nur_state.local_nurfile_path.clone(),
+        nur_state.env_path.clone(),
+        nur_state.config_path.clone(),
+        nur_state.plugins_path.clone(),
+    ];
+    files.extend(nur_state.nu_env_path.clone());
+    files.extend(nur_state.nu_config_path.clone());
+    let mut script_files = Vec::new();
+    collect_files(&nur_state.lib_dir_path, &mut script_files);
+    script_files.sort();
+    files.extend(script_files);
+    files.extend(sources.iter().cloned());
+
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    for file in files {
+        let path = file.to_string_lossy();
+        hasher.update((path.len() as u64).to_le_bytes());
+        hasher.update(path.as_bytes());
+        // Missing files need to differ from empty files
+        match fs::read(&file) {
+            Ok(contents) => {
+                hasher.update([1]);
+                hasher.update((contents.len() as u64).to_le_bytes());
+                hasher.update(contents);
+            }
+            Err(_) => hasher.update([0]),
+        }
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Files parsed by the engine which are not known to nur, like files loaded by `source` or `use`
+fn loaded_sources(nur_engine: &NurEngine) -> Vec<PathBuf> {
+    let mut sources: Vec<PathBuf> = nur_engine
+        .engine_state
+        .files()
+        .map(|file| PathBuf::from(&*file.name))
+        .filter(|path| path.is_absolute() && path.is_file())
+        .collect();
+    sources.sort();
+    sources.dedup();
+
+    sources
+}
+
+fn read_cache(nur_state: &NurState) -> Option<JsonValue> {
+    let contents = fs::read_to_string(&nur_state.task_cache_path).ok()?;
+
+    serde_json::from_str(&contents).ok()
+}
+
+impl TaskCache {
+    /// Collect the signatures of all tasks loaded into the engine
+    pub(crate) fn from_engine(nur_engine: &NurEngine) -> TaskCache {
+        TaskCache {
+            tasks: nur_engine
+                .list_task_decls()
+                .into_iter()
+                .map(|(task_name, decl_id)| {
+                    (
+                        task_name,
+                        nur_engine.engine_state.get_decl(decl_id).signature(),
+                    )
+                })
+                .collect(),
+            sources: loaded_sources(nur_engine),
+        }
+    }
+
+    /// Load the cache, returns None if there is no cache or files changed since it was written
+    pub(crate) fn load(nur_state: &NurState) -> Option<TaskCache> {
+        let cache = read_cache(nur_state)?;
+        let sources = cache["sources"]
+            .as_array()?
+            .iter()
+            .map(|source| source.as_str().map(PathBuf::from))
+            .collect::<Option<Vec<PathBuf>>>()?;
+        if cache["key"].as_str()? != cache_key(nur_state, &sources) {
+            return None;
+        }
+
+        let tasks = cache["tasks"]
+            .as_array()?
+            .iter()
+            .map(|task| {
+                Some((
+                    String::from(task["name"].as_str()?),
+                    serde_json::from_value(task["signature"].clone()).ok()?,
+                ))
+            })
+            .collect::<Option<Vec<(String, Signature)>>>()?;
+
+        Some(TaskCache { tasks, sources })
+    }
+
+    /// Write the cache for the current state of all files
+    pub(crate) fn save(&self, nur_state: &NurState) -> NurResult<()> {
+        let tasks: Vec<JsonValue> = self
+            .tasks
+            .iter()
+            .map(|(task_name, signature)| json!({"name": task_name, "signature": signature}))
+            .collect();
+        let cache = json!({
+            "key": cache_key(nur_state, &self.sources),
+            "sources": self.sources,
+            "tasks": tasks,
+        });
+
+        if let Some(parent) = nur_state.task_cache_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&nur_state.task_cache_path, cache.to_string())?;
+
+        Ok(())
+    }
+
+    /// Names of all tasks, sorted
+    pub(crate) fn task_names(&self) -> Vec<String> {
+        self.tasks
+            .iter()
+            .map(|(task_name, _)| task_name.clone())
+            .collect()
+    }
+
+    /// Signature of the task, given without the "nur" prefix
+    pub(crate) fn task_signature(&self, task_name: &str) -> Option<&Signature> {
+        self.tasks
+            .iter()
+            .find(|(name, _)| name == task_name)
+            .map(|(_, signature)| signature)
+    }
+}
+
+/// Make sure the cache matches the tasks loaded into the engine, the cache is only written when
+/// its key changed and the project has a .nur/ directory. Errors are ignored as the cache is
+/// only used to speed things up.
+pub(crate) fn update_task_cache(nur_engine: &NurEngine) {
+    if !nur_engine.state.has_project_path || !nur_engine.state.config_dir.is_dir() {
+        return;
+    }
+
+    let task_cache = TaskCache::from_engine(nur_engine);
+    let key = cache_key(&nur_engine.state, &task_cache.sources);
+    let cached_key =
+        read_cache(&nur_engine.state).and_then(|cache| cache["key"].as_str().map(String::from));
+    if cached_key.as_deref() == Some(key.as_str()) {
+        return;
+    }
+
+    let _ = task_cache.save(&nur_engine.state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::init_engine_state;
+    use crate::names::NUR_FILE;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_task_cache() {
+        let temp_dir = tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join(".nur")).unwrap();
+        fs::write(
+            temp_dir.path().join(NUR_FILE),
+            "# Build it\ndef \"nur build\" [--release] {}\ndef \"nur build docs\" [] {}",
+        )
+        .unwrap();
+
+        let nur_state =
+            NurState::new(temp_dir.path().to_path_buf(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state.clone()).unwrap();
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        assert!(TaskCache::load(&nur_state).is_none());
+        update_task_cache(&nur_engine);
+
+        let cache = TaskCache::load(&nur_state).unwrap();
+        assert_eq!(cache.task_names(), vec!["build", "build docs"]);
+        let signature = cache.task_signature("build").unwrap();
+        assert_eq!(signature.description, "Build it");
+        assert!(signature.named.iter().any(|flag| flag.long == "release"));
+
+        // Changing any nurfile invalidates the cache
+        fs::write(temp_dir.path().join(NUR_FILE), "def \"nur other\" [] {}").unwrap();
+        assert!(TaskCache::load(&nur_state).is_none());
+    }
+
+    #[test]
+    fn test_task_cache_needs_config_dir() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "def \"nur build\" [] {}").unwrap();
+
+        let nur_state =
+            NurState::new(temp_dir.path().to_path_buf(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+        nur_engine.load_nurfiles().unwrap();
+        update_task_cache(&nur_engine);
+
+        assert!(!temp_dir.path().join(".nur").exists());
+    }
+
+    #[test]
+    fn test_cache_key_includes_nu_config_and_plugins() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        let mut nur_state =
+            NurState::new(temp_dir.path().to_path_buf(), vec![String::from("nur")]).unwrap();
+        nur_state.nu_env_path = Some(temp_dir.path().join("nu-env.nu"));
+        nur_state.nu_config_path = Some(temp_dir.path().join("nu-config.nu"));
+        fs::create_dir_all(&nur_state.config_dir).unwrap();
+
+        let mut cache_keys = vec![cache_key(&nur_state, &[])];
+        for path in [
+            nur_state.nu_env_path.clone().unwrap(),
+            nur_state.nu_config_path.clone().unwrap(),
+            nur_state.plugins_path.clone(),
+        ] {
+            fs::write(path, "# changed").unwrap();
+            cache_keys.push(cache_key(&nur_state, &[]));
+        }
+
+        cache_keys.dedup();
+        assert_eq!(cache_keys.len(), 4);
+    }
+
+    #[test]
+    fn test_task_cache_includes_sourced_files() {
+        let temp_dir = tempdir().unwrap();
+        let tasks_path = temp_dir.path().join("tasks.nu");
+        fs::write(&tasks_path, "def \"nur build\" [] {}").unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "source tasks.nu").unwrap();
+        fs::create_dir(temp_dir.path().join(".nur")).unwrap();
+
+        let nur_state =
+            NurState::new(temp_dir.path().to_path_buf(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state.clone()).unwrap();
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+        update_task_cache(&nur_engine);
+
+        let cache = TaskCache::load(&nur_state).unwrap();
+        assert_eq!(cache.task_names(), vec!["build"]);
+
+        // The cache is only written when the key changed
+        let modified = fs::metadata(&nur_state.task_cache_path)
+            .unwrap()
+            .modified()
+            .unwrap();
+        update_task_cache(&nur_engine);
+        assert_eq!(
+            fs::metadata(&nur_state.task_cache_path)
+                .unwrap()
+                .modified()
+                .unwrap(),
+            modified
+        );
+
+        // Changing the sourced file invalidates the cache
+        fs::write(&tasks_path, "def \"nur other\" [] {}").unwrap();
+        assert!(TaskCache::load(&nur_state).is_none());
+    }
+}
@@ -0,0 +1,378 @@
+use crate::errors::NurResult;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Only one capture may redirect the output at a time, see OutputCapture
+static CAPTURE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Output stream of the nur process
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Writer collecting all data in memory, clones share the same buffer
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub(crate) fn new() -> SharedBuffer {
+        SharedBuffer::default()
+    }
+
+    /// Get the collected data as text
+    pub(crate) fn to_string_lossy(&self) -> String {
+        let data = self.0.lock().unwrap_or_else(|err| err.into_inner());
+
+        String::from_utf8_lossy(&data).to_string()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Where the output goes while a tee is running
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TeeMode {
+    /// To the original output and the sink
+    Copy,
+    /// Only to the sink
+    Capture,
+    /// Only to the sink, which replaces the original output, a terminal stays a terminal
+    Replace,
+}
+
+/// Copies everything written to stdout or stderr to an additional writer while it is alive
+///
+/// Output of external commands is copied too. Running tees are also stopped when the process
+/// exits (nu `exit` ends the process right away), so all output reaches the sink. Only supported
+/// on unix, other platforms will not copy any output.
+pub(crate) struct OutputTee {
+    #[cfg(unix)]
+    id: usize,
+}
+
+impl OutputTee {
+    /// Start copying the output stream to the sink
+    pub(crate) fn start(stream: OutputStream, sink: Box<dyn Write + Send>) -> NurResult<OutputTee> {
+        OutputTee::_start(stream, sink, TeeMode::Copy)
+    }
+
+    /// Start sending the output stream to the sink only, the sink is responsible for writing to
+    /// the original output
+    ///
+    /// When the output is a terminal the stream is replaced by a pseudo terminal, so colours,
+    /// progress bars and `is-terminal` keep working (only changes of the terminal size are not
+    /// passed on).
+    pub(crate) fn replace(
+        stream: OutputStream,
+        sink: Box<dyn Write + Send>,
+    ) -> NurResult<OutputTee> {
+        OutputTee::_start(stream, sink, TeeMode::Replace)
+    }
+
+    fn _start(
+        stream: OutputStream,
+        sink: Box<dyn Write + Send>,
+        mode: TeeMode,
+    ) -> NurResult<OutputTee> {
+        #[cfg(unix)]
+        {
+            Ok(OutputTee {
+                id: unix::start_tee(stream, sink, mode)?,
+            })
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = (stream, sink, mode);
+            Ok(OutputTee {})
+        }
+    }
+}
+
+impl Drop for OutputTee {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unix::stop_tee(self.id);
+    }
+}
+
+/// Captures everything written to stdout and stderr while it is alive, instead of printing it
+///
+/// Captures are process wide, so only one capture runs at a time. Only supported on unix, other
+/// platforms will print the output and capture nothing.
+pub(crate) struct OutputCapture {
+    stdout: SharedBuffer,
+    stderr: SharedBuffer,
+    tees: Vec<OutputTee>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl OutputCapture {
+    pub(crate) fn start() -> NurResult<OutputCapture> {
+        let lock = CAPTURE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let stdout = SharedBuffer::new();
+        let stderr = SharedBuffer::new();
+        let tees = vec![
+            OutputTee::_start(
+                OutputStream::Stdout,
+                Box::new(stdout.clone()),
+                TeeMode::Capture,
+            )?,
+            OutputTee::_start(
+                OutputStream::Stderr,
+                Box::new(stderr.clone()),
+                TeeMode::Capture,
+            )?,
+        ];
+
+        Ok(OutputCapture {
+            stdout,
+            stderr,
+            tees,
+            _lock: lock,
+        })
+    }
+
+    /// Stop capturing, returns the captured stdout and stderr
+    pub(crate) fn finish(mut self) -> (String, String) {
+        // Stop in reverse order, so stdout and stderr are restored to their original output
+        while self.tees.pop().is_some() {}
+
+        (self.stdout.to_string_lossy(), self.stderr.to_string_lossy())
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::{OutputStream, TeeMode};
+    use crate::errors::NurResult;
+    use std::fs::File;
+    use std::io::{self, Read, Write};
+    use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Mutex, Once};
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, Instant};
+
+    // The copy thread may be kept alive by background processes still holding the output
+    const TEE_EXIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+    // Running tees in the order they were started, by id
+    static RUNNING_TEES: Mutex<Vec<(usize, TeeStream)>> = Mutex::new(Vec::new());
+    static NEXT_TEE_ID: AtomicUsize = AtomicUsize::new(0);
+    static REGISTER_EXIT_HANDLER: Once = Once::new();
+
+    pub(super) struct TeeStream {
+        fd: RawFd,
+        original: OwnedFd,
+        thread: JoinHandle<()>,
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    fn redirect(from: RawFd, to: RawFd) -> io::Result<()> {
+        // SAFETY: dup2 only replaces the file descriptor "to", both are valid
+        if unsafe { libc::dup2(from, to) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    fn create_pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+        let mut fds = [0; 2];
+        // SAFETY: pipe will write two new file descriptors into the array
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // SAFETY: both file descriptors were just created and are owned by us
+        Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+    }
+
+    /// Create a pseudo terminal with the size of the original terminal, returns (reader, writer)
+    fn open_pty(original: &OwnedFd) -> io::Result<(OwnedFd, OwnedFd)> {
+        let mut master: RawFd = -1;
+        let mut slave: RawFd = -1;
+        // SAFETY: all pointers are valid for the duration of the calls, the returned file
+        // descriptors are owned by us
+        unsafe {
+            let mut size: libc::winsize = std::mem::zeroed();
+            let size_ptr: *mut libc::winsize =
+                if libc::ioctl(original.as_raw_fd(), libc::TIOCGWINSZ, &mut size) == 0 {
+                    &mut size
+                } else {
+                    std::ptr::null_mut()
+                };
+            check(libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                size_ptr,
+            ))?;
+            let (master, slave) = (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave));
+
+            // Pass all output on unchanged, the real terminal does the processing
+            let mut termios: libc::termios = std::mem::zeroed();
+            check(libc::tcgetattr(slave.as_raw_fd(), &mut termios))?;
+            libc::cfmakeraw(&mut termios);
+            check(libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios))?;
+
+            // Only the redirected stdout/stderr may be passed on to child processes
+            for fd in [master.as_raw_fd(), slave.as_raw_fd()] {
+                check(libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC))?;
+            }
+
+            Ok((master, slave))
+        }
+    }
+
+    fn copy_output(
+        reader: OwnedFd,
+        original: OwnedFd,
+        mut sink: Box<dyn Write + Send>,
+        passthrough: bool,
+    ) {
+        let mut reader = File::from(reader);
+        let mut original = File::from(original);
+        let mut buffer = [0; 8192];
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                // Includes reading a pseudo terminal after all writers are closed
+                Err(_) => break,
+            };
+            if passthrough {
+                let _ = original.write_all(&buffer[..read]);
+            }
+            let _ = sink.write_all(&buffer[..read]);
+        }
+        let _ = sink.flush();
+    }
+
+    fn running_tees() -> std::sync::MutexGuard<'static, Vec<(usize, TeeStream)>> {
+        RUNNING_TEES.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Stop all running tees, newest first so every stream is restored to its original output
+    extern "C" fn stop_running_tees() {
+        let tees = std::mem::take(&mut *running_tees());
+        for (_, stream) in tees.into_iter().rev() {
+            stop_stream(stream);
+        }
+    }
+
+    pub(super) fn start_tee(
+        stream: OutputStream,
+        sink: Box<dyn Write + Send>,
+        mode: TeeMode,
+    ) -> NurResult<usize> {
+        REGISTER_EXIT_HANDLER.call_once(|| {
+            // SAFETY: the handler is a plain function without arguments, called once on exit
+            unsafe { libc::atexit(stop_running_tees) };
+        });
+
+        let fd = match stream {
+            OutputStream::Stdout => {
+                io::stdout().flush()?;
+                libc::STDOUT_FILENO
+            }
+            OutputStream::Stderr => {
+                io::stderr().flush()?;
+                libc::STDERR_FILENO
+            }
+        };
+
+        // SAFETY: stdout and stderr are open for the whole process lifetime
+        let original = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+        // SAFETY: the file descriptor is valid
+        let (reader, writer) = if mode == TeeMode::Replace && unsafe { libc::isatty(fd) } == 1 {
+            open_pty(&original)?
+        } else {
+            create_pipe()?
+        };
+        let passthrough = mode == TeeMode::Copy;
+        let thread_original = original.try_clone()?;
+        let thread = thread::spawn(move || copy_output(reader, thread_original, sink, passthrough));
+        redirect(writer.as_raw_fd(), fd)?;
+
+        let id = NEXT_TEE_ID.fetch_add(1, Ordering::Relaxed);
+        running_tees().push((
+            id,
+            TeeStream {
+                fd,
+                original,
+                thread,
+            },
+        ));
+
+        Ok(id)
+    }
+
+    pub(super) fn stop_tee(id: usize) {
+        let stream = {
+            let mut tees = running_tees();
+            match tees.iter().position(|(tee_id, _)| *tee_id == id) {
+                Some(index) => tees.remove(index).1,
+                // Already stopped on exit
+                None => return,
+            }
+        };
+        stop_stream(stream);
+    }
+
+    fn stop_stream(stream: TeeStream) {
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+
+        // Restoring the original output closes the pipe, so the thread will finish
+        let _ = redirect(stream.original.as_raw_fd(), stream.fd);
+
+        let started_at = Instant::now();
+        while !stream.thread.is_finished() && started_at.elapsed() < TEE_EXIT_TIMEOUT {
+            thread::sleep(Duration::from_millis(10));
+        }
+        if stream.thread.is_finished() {
+            let _ = stream.thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_buffer() {
+        let buffer = SharedBuffer::new();
+        let mut writer = buffer.clone();
+        write!(writer, "some ").unwrap();
+        write!(writer, "output").unwrap();
+
+        assert_eq!(buffer.to_string_lossy(), "some output");
+    }
+}
@@ -0,0 +1,420 @@
+use crate::args::is_safe_taskname;
+use crate::engine::NurEngine;
+use crate::errors::NurResult;
+use crate::names::NUR_NAME;
+use nu_ansi_term::Color;
+use nu_protocol::ast::{Argument, Block, Expr, Expression, ExternalArgument, ListItem};
+use nu_protocol::engine::{EngineState, StateWorkingSet};
+use nu_protocol::{report_parse_error, DeclId, VarId};
+use nu_utils::escape_quote_string;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Problem found by the nurfile checks, not including parse errors
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CheckIssue {
+    pub file: String,
+    pub message: String,
+}
+
+/// Parse the given file without evaluating it, reporting all parse errors
+fn check_parse_file<P: AsRef<Path>>(nur_engine: &NurEngine, file_path: P) -> NurResult<usize> {
+    let file_path = file_path.as_ref();
+    let contents = fs::read(file_path)?;
+
+    let mut working_set = StateWorkingSet::new(&nur_engine.engine_state);
+    nu_parser::parse(&mut working_set, file_path.to_str(), &contents, false);
+
+    for err in &working_set.parse_errors {
+        report_parse_error(&working_set, err);
+    }
+
+    Ok(working_set.parse_errors.len())
+}
+
+/// Parse the given module by using it, this will report all parse errors inside the module
+fn check_parse_module<P: AsRef<Path>>(nur_engine: &NurEngine, module_path: P) -> usize {
+    let use_call = format!(
+        "use {}",
+        escape_quote_string(&module_path.as_ref().to_string_lossy())
+    );
+
+    let mut working_set = StateWorkingSet::new(&nur_engine.engine_state);
+    nu_parser::parse(&mut working_set, None, use_call.as_bytes(), false);
+
+    for err in &working_set.parse_errors {
+        report_parse_error(&working_set, err);
+    }
+
+    working_set.parse_errors.len()
+}
+
+fn find_modules<P: AsRef<Path>>(dir_path: P) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir_path) else {
+        return vec![];
+    };
+
+    let mut modules = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if path.join("mod.nu").is_file() {
+                modules.push(path);
+            } else {
+                modules.extend(find_modules(&path));
+            }
+        } else if path.extension().is_some_and(|extension| extension == "nu") {
+            modules.push(path);
+        }
+    }
+    modules.sort();
+
+    modules
+}
+
+fn file_name<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref()
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Whether the block passes the variable to some call using the spread operator (`...$var`)
+fn block_spreads_var(engine_state: &EngineState, block: &Block, var_id: VarId) -> bool {
+    block
+        .pipelines
+        .iter()
+        .flat_map(|pipeline| &pipeline.elements)
+        .any(|element| expr_spreads_var(engine_state, &element.expr, var_id))
+}
+
+fn expr_spreads_var(engine_state: &EngineState, expr: &Expression, var_id: VarId) -> bool {
+    let spreads_var = |expr: &Expression| expr_spreads_var(engine_state, expr, var_id);
+    // The spread variable is usually parsed as a cell path without members
+    let is_var = |expr: &Expression| match &expr.expr {
+        Expr::Var(id) => *id == var_id,
+        Expr::FullCellPath(cell_path) => {
+            cell_path.tail.is_empty()
+                && matches!(cell_path.head.expr, Expr::Var(id) if id == var_id)
+        }
+        _ => false,
+    };
+
+    match &expr.expr {
+        Expr::Call(call) => call.arguments.iter().any(|argument| match argument {
+            Argument::Spread(spread) => is_var(spread) || spreads_var(spread),
+            _ => argument.expr().is_some_and(spreads_var),
+        }),
+        Expr::ExternalCall(head, arguments) => {
+            spreads_var(head)
+                || arguments.iter().any(|argument| match argument {
+                    ExternalArgument::Spread(spread) => is_var(spread) || spreads_var(spread),
+                    ExternalArgument::Regular(regular) => spreads_var(regular),
+                })
+        }
+        Expr::Block(block_id)
+        | Expr::Closure(block_id)
+        | Expr::Subexpression(block_id)
+        | Expr::RowCondition(block_id) => {
+            block_spreads_var(engine_state, engine_state.get_block(*block_id), var_id)
+        }
+        Expr::Keyword(keyword) => spreads_var(&keyword.expr),
+        Expr::UnaryNot(inner) | Expr::Collect(_, inner) => spreads_var(inner),
+        Expr::BinaryOp(lhs, _, rhs) => spreads_var(lhs) || spreads_var(rhs),
+        Expr::FullCellPath(cell_path) => spreads_var(&cell_path.head),
+        Expr::List(items) => items.iter().any(|item| match item {
+            ListItem::Item(item) | ListItem::Spread(_, item) => spreads_var(item),
+        }),
+        Expr::MatchBlock(arms) => arms.iter().any(|(_, arm)| spreads_var(arm)),
+        Expr::StringInterpolation(parts) | Expr::GlobInterpolation(parts, _) => {
+            parts.iter().any(spreads_var)
+        }
+        _ => false,
+    }
+}
+
+/// Check the loaded tasks for nur specific problems
+fn check_tasks(
+    nur_engine: &NurEngine,
+    task_files: &HashMap<String, String>,
+    shadowed_tasks: &[String],
+) -> Vec<CheckIssue> {
+    let mut issues = Vec::new();
+    let task_decls: Vec<(String, DeclId)> = nur_engine.list_task_decls();
+
+    for (task_name, decl_id) in &task_decls {
+        let file = task_files.get(task_name).cloned().unwrap_or_default();
+        let mut add_issue = |message: String| {
+            issues.push(CheckIssue {
+                file: file.clone(),
+                message,
+            })
+        };
+        let decl = nur_engine.engine_state.get_decl(*decl_id);
+        let signature = decl.signature();
+
+        if !task_name.split(' ').all(is_safe_taskname) {
+            add_issue(format!(
+                "Task '{}' has an unsafe name and cannot be called",
+                task_name
+            ));
+        }
+
+        if shadowed_tasks.contains(task_name) {
+            add_issue(format!(
+                "Task '{}' from nurfile is shadowed by nurfile.local",
+                task_name
+            ));
+        }
+
+        if let Some((parent_name, _)) = task_name.rsplit_once(' ') {
+            if !task_decls.iter().any(|(name, _)| name == parent_name) {
+                add_issue(format!(
+                    "Sub task '{}' has no parent task '{}'",
+                    task_name, parent_name
+                ));
+            }
+        }
+
+        if decl.description().trim().is_empty() {
+            add_issue(format!("Task '{}' has no description", task_name));
+        }
+
+        if let (Some(rest), Some(block_id)) = (&signature.rest_positional, decl.block_id()) {
+            let forwards_args = rest.var_id.is_some_and(|var_id| {
+                let engine_state = &nur_engine.engine_state;
+                block_spreads_var(engine_state, engine_state.get_block(block_id), var_id)
+            });
+            if forwards_args && !signature.allows_unknown_args {
+                add_issue(format!(
+                    "Task '{}' forwards ...${} but is not defined using --wrapped, flags will not be passed",
+                    task_name, rest.name
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+fn task_decl_map(nur_engine: &NurEngine) -> HashMap<String, DeclId> {
+    nur_engine.list_task_decls().into_iter().collect()
+}
+
+/// Check all nurfiles, env.nu, config.nu and the scripts lib dir for problems
+///
+/// Reports all parse errors and nur specific problems, returns the number of problems found.
+pub(crate) fn check_project(nur_engine: &mut NurEngine, use_color: bool) -> NurResult<usize> {
+    let mut problems = 0;
+
+    // Check env and config, those need to be loaded for the nurfiles to be parsed correctly
+    let env_path = nur_engine.state.env_path.clone();
+    let env_errors = if env_path.exists() {
+        check_parse_file(nur_engine, &env_path)?
+    } else {
+        0
+    };
+    if env_errors == 0 {
+        nur_engine.load_env()?;
+    }
+    problems += env_errors;
+    let config_path = nur_engine.state.config_path.clone();
+    let config_errors = if config_path.exists() {
+        check_parse_file(nur_engine, &config_path)?
+    } else {
+        0
+    };
+    if config_errors == 0 {
+        nur_engine.load_config()?;
+    }
+    problems += config_errors;
+
+    // Check all modules in lib dir
+    for module_path in find_modules(&nur_engine.state.lib_dir_path) {
+        problems += check_parse_module(nur_engine, &module_path);
+    }
+
+    // Check nurfiles, remembering where the tasks were defined
+    let mut task_files: HashMap<String, String> = HashMap::new();
+    let mut shadowed_tasks: Vec<String> = Vec::new();
+    for nurfile_path in [
+        nur_engine.state.nurfile_path.clone(),
+        nur_engine.state.local_nurfile_path.clone(),
+    ] {
+        if !nurfile_path.exists() {
+            continue;
+        }
+        let parse_errors = check_parse_file(nur_engine, &nurfile_path)?;
+        if parse_errors > 0 {
+            problems += parse_errors;
+            continue;
+        }
+
+        let tasks_before = task_decl_map(nur_engine);
+        nur_engine.source(&nurfile_path, nu_protocol::PipelineData::empty())?;
+        for (task_name, decl_id) in task_decl_map(nur_engine) {
+            match tasks_before.get(&task_name) {
+                Some(previous_decl_id) if *previous_decl_id != decl_id => {
+                    shadowed_tasks.push(task_name.clone());
+                }
+                Some(_) => continue,
+                None => {}
+            }
+            task_files.insert(task_name, file_name(&nurfile_path));
+        }
+    }
+
+    let issues = check_tasks(nur_engine, &task_files, &shadowed_tasks);
+    for issue in &issues {
+        eprintln!(
+            "{}{}: {}{}",
+            if use_color {
+                Color::Yellow.prefix().to_string()
+            } else {
+                String::from("")
+            },
+            if issue.file.is_empty() {
+                NUR_NAME
+            } else {
+                &issue.file
+            },
+            issue.message,
+            if use_color {
+                Color::Yellow.suffix().to_string()
+            } else {
+                String::from("")
+            },
+        );
+    }
+    problems += issues.len();
+
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::init_engine_state;
+    use crate::names::{
+        NUR_CONFIG_DIR, NUR_CONFIG_ENV_FILENAME, NUR_CONFIG_LIB_PATH, NUR_FILE, NUR_LOCAL_FILE,
+    };
+    use crate::state::NurState;
+    use tempfile::{tempdir, TempDir};
+
+    fn _prepare_nur_engine(temp_dir: &TempDir, nurfile: &str) -> NurEngine {
+        let temp_dir_path = temp_dir.path().to_path_buf();
+        fs::write(temp_dir.path().join(NUR_FILE), nurfile).unwrap();
+
+        let nur_state = NurState::new(temp_dir_path.clone(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir_path).unwrap();
+
+        NurEngine::new(engine_state, nur_state).unwrap()
+    }
+
+    fn _messages(nur_engine: &mut NurEngine) -> Vec<String> {
+        let mut task_files = HashMap::new();
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+        for (task_name, _) in nur_engine.list_task_decls() {
+            task_files.insert(task_name, String::from(NUR_FILE));
+        }
+
+        check_tasks(nur_engine, &task_files, &[])
+            .into_iter()
+            .map(|issue| issue.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_check_project_without_problems() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(
+            &temp_dir,
+            "# Some task\ndef \"nur some-task\" [] {}\n# Some sub task\ndef \"nur some-task sub\" [] {}",
+        );
+
+        assert_eq!(check_project(&mut nur_engine, false).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_check_project_reports_all_parse_errors() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(
+            &temp_dir,
+            "# Some task\ndef \"nur some-task\" [] { let }\n# Other task\ndef \"nur other-task\" [] { let }",
+        );
+        let scripts_dir = temp_dir
+            .path()
+            .join(NUR_CONFIG_DIR)
+            .join(NUR_CONFIG_LIB_PATH);
+        fs::create_dir_all(&scripts_dir).unwrap();
+        fs::write(scripts_dir.join("broken.nu"), "export def broken [ {}").unwrap();
+
+        assert!(check_project(&mut nur_engine, false).unwrap() >= 3);
+    }
+
+    #[test]
+    fn test_check_project_counts_all_env_parse_errors() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine =
+            _prepare_nur_engine(&temp_dir, "# Some task\ndef \"nur some-task\" [] {}");
+        let config_dir = temp_dir.path().join(NUR_CONFIG_DIR);
+        fs::create_dir_all(&config_dir).unwrap();
+        let env_contents = "$env.FOO = (1 +)\n$env.BAR = (2 +)";
+        fs::write(config_dir.join(NUR_CONFIG_ENV_FILENAME), env_contents).unwrap();
+        let mut working_set = StateWorkingSet::new(&nur_engine.engine_state);
+        nu_parser::parse(&mut working_set, None, env_contents.as_bytes(), false);
+        let parse_errors = working_set.parse_errors.len();
+
+        assert!(parse_errors > 1);
+        assert_eq!(check_project(&mut nur_engine, false).unwrap(), parse_errors);
+    }
+
+    #[test]
+    fn test_check_project_reports_shadowed_tasks() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine =
+            _prepare_nur_engine(&temp_dir, "# Some task\ndef \"nur some-task\" [] {}");
+        fs::write(
+            temp_dir.path().join(NUR_LOCAL_FILE),
+            "# Local task\ndef \"nur some-task\" [] {}",
+        )
+        .unwrap();
+
+        assert_eq!(check_project(&mut nur_engine, false).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_check_tasks_finds_problems() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(
+            &temp_dir,
+            "def \"nur no-docs\" [] {}\n# Orphan\ndef \"nur missing sub\" [] {}\n# Forwarding\ndef \"nur forward\" [...args] { ^echo ...$args }\n# Wrapped\ndef --wrapped \"nur wrapped\" [...args] { ^echo ...$args }\n# Nested\ndef \"nur nested\" [...rest] { do { nur forward ...$rest } }\n# Printing\ndef \"nur printing\" [...args] { print \"...$args\" }",
+        );
+
+        assert_eq!(
+            _messages(&mut nur_engine),
+            vec![
+                String::from("Task 'forward' forwards ...$args but is not defined using --wrapped, flags will not be passed"),
+                String::from("Sub task 'missing sub' has no parent task 'missing'"),
+                String::from("Task 'nested' forwards ...$rest but is not defined using --wrapped, flags will not be passed"),
+                String::from("Task 'no-docs' has no description"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_tasks_finds_unsafe_names() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir, "# Unsafe\ndef \"nur it's\" [] {}");
+
+        assert_eq!(
+            _messages(&mut nur_engine),
+            vec![String::from(
+                "Task 'it's' has an unsafe name and cannot be called"
+            )]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use miette::Diagnostic;
+use nu_protocol::engine::CachedFile;
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// CI system nur is running in, used to format the output for CI logs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CiProvider {
+    GitHubActions,
+    GitLab,
+    Generic,
+}
+
+fn env_is_true(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| matches!(value.to_lowercase().as_str(), "true" | "1"))
+}
+
+impl CiProvider {
+    /// Detect the CI system from the environment, returns None when not running in CI
+    pub(crate) fn detect() -> Option<CiProvider> {
+        if env_is_true("GITHUB_ACTIONS") {
+            Some(CiProvider::GitHubActions)
+        } else if env_is_true("GITLAB_CI") {
+            Some(CiProvider::GitLab)
+        } else if env_is_true("CI") {
+            Some(CiProvider::Generic)
+        } else {
+            None
+        }
+    }
+
+    /// Line starting a collapsible group in the CI log
+    pub(crate) fn group_start(&self, title: &str) -> String {
+        match self {
+            CiProvider::GitHubActions => format!("::group::{}", title),
+            CiProvider::GitLab => format!(
+                "\x1b[0Ksection_start:{}:{}[collapsed=true]\r\x1b[0K{}",
+                unix_timestamp(),
+                gitlab_section_name(title),
+                title
+            ),
+            CiProvider::Generic => format!("--- {}", title),
+        }
+    }
+
+    /// Line ending the collapsible group started for the title
+    pub(crate) fn group_end(&self, title: &str) -> String {
+        match self {
+            CiProvider::GitHubActions => String::from("::endgroup::"),
+            CiProvider::GitLab => format!(
+                "\x1b[0Ksection_end:{}:{}\r\x1b[0K",
+                unix_timestamp(),
+                gitlab_section_name(title)
+            ),
+            CiProvider::Generic => String::new(),
+        }
+    }
+
+    /// Annotation pointing to the source of the error, only supported by GitHub Actions
+    pub(crate) fn error_annotation<'a>(
+        &self,
+        files: impl Iterator<Item = &'a CachedFile>,
+        diagnostic: &dyn Diagnostic,
+    ) -> Option<String> {
+        if *self != CiProvider::GitHubActions {
+            return None;
+        }
+
+        let mut message = diagnostic.to_string().trim_end_matches('.').to_string();
+        let label = diagnostic.labels().and_then(|mut labels| labels.next());
+        if let Some(label_text) = label.as_ref().and_then(|label| label.label()) {
+            message.push_str(&format!(": {}", label_text));
+        }
+        let location = label.and_then(|label| source_location(files, label.offset()));
+
+        Some(match location {
+            Some((file_name, line, column)) => format!(
+                "::error file={},line={},col={}::{}",
+                escape_annotation_property(&file_name),
+                line,
+                column,
+                escape_annotation_data(&message)
+            ),
+            None => format!("::error::{}", escape_annotation_data(&message)),
+        })
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn gitlab_section_name(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Find file name, line and column (both starting at 1) for the global span offset
+fn source_location<'a>(
+    mut files: impl Iterator<Item = &'a CachedFile>,
+    offset: usize,
+) -> Option<(String, usize, usize)> {
+    let file =
+        files.find(|file| file.covered_span.start <= offset && offset < file.covered_span.end)?;
+    let before = file.content.get(..offset - file.covered_span.start)?;
+    let line = before.iter().filter(|c| **c == b'\n').count() + 1;
+    let column = before.iter().rev().take_while(|c| **c != b'\n').count() + 1;
+
+    // Annotations need paths relative to the checked out repository
+    let file_name = match env::var("GITHUB_WORKSPACE") {
+        Ok(workspace) => file
+            .name
+            .strip_prefix(&format!("{}/", workspace))
+            .unwrap_or(&file.name)
+            .to_string(),
+        Err(_) => file.name.to_string(),
+    };
+
+    Some((file_name, line, column))
+}
+
+fn escape_annotation_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_annotation_property(property: &str) -> String {
+    escape_annotation_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::{ShellError, Span};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_group_markers() {
+        assert_eq!(
+            CiProvider::GitHubActions.group_start("nur some-task"),
+            "::group::nur some-task"
+        );
+        assert_eq!(
+            CiProvider::GitHubActions.group_end("nur some-task"),
+            "::endgroup::"
+        );
+        assert!(CiProvider::GitLab
+            .group_start("nur some-task")
+            .contains(":nur_some-task[collapsed=true]\r\x1b[0Knur some-task"));
+        assert!(CiProvider::GitLab
+            .group_end("nur some-task")
+            .contains(":nur_some-task\r"));
+    }
+
+    #[test]
+    fn test_error_annotation() {
+        let files = [CachedFile {
+            name: Arc::from("/project/nurfile"),
+            content: Arc::from(b"def \"nur a\" [] {\n    error make\n}".as_slice()),
+            covered_span: Span::new(100, 133),
+        }];
+        let err = ShellError::GenericError {
+            error: String::from("Something failed"),
+            msg: String::from("here"),
+            span: Some(Span::new(121, 131)),
+            help: None,
+            inner: vec![],
+        };
+
+        assert_eq!(
+            CiProvider::GitHubActions.error_annotation(files.iter(), &err),
+            Some(String::from(
+                "::error file=/project/nurfile,line=2,col=5::Something failed: here"
+            ))
+        );
+        assert_eq!(
+            CiProvider::GitLab.error_annotation(files.iter(), &err),
+            None
+        );
+    }
+}
@@ -0,0 +1,607 @@
+use crate::cache::{update_task_cache, TaskCache};
+use crate::capture::{OutputStream, OutputTee, SharedBuffer};
+use crate::check::check_project;
+use crate::ci::CiProvider;
+use crate::commands::Nur;
+use crate::compat::show_nurscripts_hint;
+use crate::completions::{complete_commandline, complete_commandline_cached, completion_script};
+use crate::engine::{compiled_command_sets, init_engine_state, NurEngine};
+use crate::errors::NurError;
+use crate::events::{track_calls, EventWriter};
+use crate::fmt::format_project;
+use crate::history::{record_task_run, replay_last_task_call, show_history, wants_replay};
+use crate::init::init_project;
+use crate::logs::TaskLog;
+#[cfg(feature = "lsp")]
+use crate::lsp::run_language_server;
+use crate::path::current_dir_from_environment;
+use crate::profile::StartupProfile;
+use crate::report::{Report, TaskResult, TaskResults};
+use crate::secrets::{mask_secrets, secret_values, OutputMasking};
+use crate::state::NurState;
+use miette::Result;
+use nu_ansi_term::Color;
+use nu_protocol::{ByteStream, PipelineData, Span};
+use serde_json::json;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+use std::time::Instant;
+
+/// Run the nur command line using the arguments of the current process, used by the nur binary
+pub fn run_cli() -> Result<ExitCode, miette::ErrReport> {
+    // Initialise nur state
+    let mut startup_profile = StartupProfile::new();
+    let run_path = current_dir_from_environment();
+    let args: Vec<String> = env::args().collect();
+
+    // Handle completion requests (hidden, used by the scripts generated by --completions)
+    if args.len() == 3 && args[1] == "--complete" {
+        let nur_state = NurState::new(run_path, vec![args[0].clone()])?;
+
+        // Use the cached task signatures if possible, setting up the engine is way slower
+        let cached_completions = TaskCache::load(&nur_state)
+            .and_then(|task_cache| complete_commandline_cached(&task_cache, &nur_state, &args[2]));
+        let completions = match cached_completions {
+            Some(completions) => completions,
+            None => {
+                let engine_state = init_engine_state(nur_state.project_path())?;
+                let mut nur_engine = NurEngine::new(engine_state, nur_state)?;
+                if nur_engine.state().has_project_path() {
+                    nur_engine.load_env()?;
+                    nur_engine.load_config()?;
+                    nur_engine.load_nurfiles()?;
+                    update_task_cache(&nur_engine);
+                }
+
+                complete_commandline(&mut nur_engine, &args[2])
+            }
+        };
+        for completion in completions {
+            println!("{}\t{}", completion.value, completion.description);
+        }
+
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // List tasks from the cache, used by many completion scripts
+    if args.len() == 2 && (args[1] == "--list" || args[1] == "-l") {
+        let nur_state = NurState::new(run_path.clone(), vec![args[0].clone()])?;
+        if let Some(task_cache) = TaskCache::load(&nur_state) {
+            for task_name in task_cache.task_names() {
+                println!("{}", task_name);
+            }
+
+            return Ok(ExitCode::SUCCESS);
+        }
+    }
+
+    let mut nur_state = NurState::new(run_path, args)?;
+
+    // Replay the last task call (--again or "nur !!"), needs to happen before the engine is set up
+    if nur_state.has_project_path() && wants_replay(&nur_state) {
+        replay_last_task_call(&mut nur_state)?;
+    }
+
+    // Create raw nu engine state
+    let engine_state = init_engine_state(nur_state.project_path())?;
+    startup_profile.phase_done("nu engine (commands, std library)");
+
+    // Setup nur engine from engine state
+    let mut nur_engine = NurEngine::new(engine_state, nur_state)?;
+
+    // Parse args
+    let parsed_nur_args = nur_engine.parse_args()?;
+    if parsed_nur_args.show_version {
+        println!(
+            "{}\ncommands: {}",
+            env!("CARGO_PKG_VERSION"),
+            compiled_command_sets().join(", ")
+        );
+
+        return Ok(ExitCode::SUCCESS);
+    }
+    startup_profile.phase_done("nur engine and arguments");
+
+    // Setup CI output, either detected or forced by --ci (detection is disabled by --no-ci)
+    if parsed_nur_args.ci_mode && parsed_nur_args.no_ci {
+        return Err(miette::ErrReport::from(NurError::InvalidNurCall(
+            String::from("--ci"),
+            String::from("--no-ci"),
+        )));
+    }
+    let ci_provider = if parsed_nur_args.no_ci {
+        None
+    } else {
+        CiProvider::detect().or(parsed_nur_args.ci_mode.then_some(CiProvider::Generic))
+    };
+    if let Some(ci_provider) = ci_provider {
+        nur_engine.set_ci_provider(ci_provider)?;
+    }
+    let use_color = nur_engine.engine_state().get_config().use_ansi_coloring;
+
+    // Setup machine readable events, if requested
+    let events = match &parsed_nur_args.events {
+        Some(events) => Some(EventWriter::from_argument(
+            &events.item,
+            nur_engine.state().run_path(),
+        )?),
+        None => None,
+    };
+    if let Some(events) = &events {
+        if nur_engine.state().has_project_path() {
+            events.emit(
+                "project_discovered",
+                json!({
+                    "project_path": nur_engine.state().project_path(),
+                    "run_path": nur_engine.state().run_path(),
+                }),
+            );
+        }
+    }
+
+    // Show hints for compatibility issues
+    if nur_engine.state().has_project_path() {
+        show_nurscripts_hint(nur_engine.state().project_path(), use_color);
+    }
+
+    // Handle completion script output, does not need any project
+    if let Some(shell) = parsed_nur_args.completions {
+        print!("{}", completion_script(&shell.item)?);
+
+        std::process::exit(0);
+    }
+
+    // Handle project setup, will create the nurfile and .nur/ in the current directory
+    if parsed_nur_args.init_project {
+        let created_paths = init_project(
+            &mut nur_engine,
+            parsed_nur_args
+                .init_from
+                .as_ref()
+                .map(|from| from.item.as_str()),
+            parsed_nur_args.force,
+        )?;
+        for created_path in created_paths {
+            println!("Created {}", created_path.to_string_lossy());
+        }
+
+        std::process::exit(0);
+    }
+
+    // Handle language server, works without project as well (only knowing about $nur then)
+    #[cfg(feature = "lsp")]
+    if parsed_nur_args.run_lsp {
+        run_language_server(&mut nur_engine)?;
+
+        std::process::exit(0);
+    }
+
+    // Handle execution without project path, only allow to show help, abort otherwise
+    if !nur_engine.state().has_project_path() {
+        if parsed_nur_args.show_help {
+            nur_engine.print_help(&Nur::default());
+
+            std::process::exit(0);
+        } else {
+            return Err(miette::ErrReport::from(NurError::NurfileNotFound()));
+        }
+    }
+
+    // Handle formatting, together with --check files will only be checked
+    if parsed_nur_args.format_files {
+        let changed_paths = format_project(&mut nur_engine, parsed_nur_args.check_project)?;
+        for changed_path in &changed_paths {
+            if parsed_nur_args.check_project {
+                eprintln!("Needs formatting: {}", changed_path.to_string_lossy());
+            } else {
+                println!("Formatted {}", changed_path.to_string_lossy());
+            }
+        }
+
+        if parsed_nur_args.check_project && !changed_paths.is_empty() {
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    // Handle checking the project, this will load all files itself
+    if parsed_nur_args.check_project {
+        let problems = check_project(&mut nur_engine, use_color)?;
+        if problems == 0 {
+            println!("No problems found");
+            std::process::exit(0);
+        } else {
+            eprintln!("Found {} problem(s)", problems);
+            std::process::exit(1);
+        }
+    }
+
+    // Handle history output, the task call is used to filter by task
+    if parsed_nur_args.show_history {
+        let task_filter = if nur_engine.state().has_task_call() {
+            Some(nur_engine.state().task_call()[1..].join(" "))
+        } else {
+            None
+        };
+        let exit_code = show_history(
+            &mut nur_engine,
+            task_filter.as_deref(),
+            parsed_nur_args.failed_only,
+        )?;
+
+        std::process::exit(exit_code);
+    }
+
+    // Load secrets, env and config
+    startup_profile.phase_done("nur options");
+    nur_engine.load_secrets()?;
+    startup_profile.phase_done("secrets");
+    nur_engine.load_env()?;
+    startup_profile.phase_done("env.nu");
+    if let Some(events) = &events {
+        events.emit("env_loaded", json!({"path": nur_engine.state().env_path()}));
+    }
+    nur_engine.load_config()?;
+    startup_profile.phase_done("config.nu");
+    if let Some(events) = &events {
+        events.emit(
+            "config_loaded",
+            json!({"path": nur_engine.state().config_path()}),
+        );
+    }
+
+    // Load task files
+    nur_engine.load_nurfiles()?;
+    startup_profile.phase_done("nurfiles");
+    update_task_cache(&nur_engine);
+    startup_profile.phase_done("task cache");
+    if parsed_nur_args.profile_startup {
+        startup_profile.print();
+    }
+    if let Some(events) = &events {
+        events.emit(
+            "nurfiles_loaded",
+            json!({"tasks": nur_engine.list_tasks().len()}),
+        );
+    }
+
+    // Handle list tasks
+    if parsed_nur_args.list_tasks {
+        for task_name in nur_engine.list_tasks() {
+            println!("{}", task_name);
+        }
+
+        std::process::exit(0);
+    }
+
+    // Show help if no task call was found
+    // (error exit if --help was not passed)
+    if !nur_engine.state().has_task_call()
+        && parsed_nur_args.run_commands.is_none()
+        && !parsed_nur_args.enter_shell
+    {
+        nur_engine.print_help(&Nur::default());
+        if parsed_nur_args.show_help {
+            std::process::exit(0);
+        } else {
+            std::process::exit(1);
+        }
+    }
+
+    // Handle help
+    if parsed_nur_args.show_help {
+        if !nur_engine.state().has_task_call() {
+            nur_engine.print_help(&Nur::default());
+            std::process::exit(0);
+        }
+
+        if let Some(command) = nur_engine.clone().get_task_def() {
+            nur_engine.clone().print_help(command);
+            std::process::exit(0);
+        } else {
+            return Err(miette::ErrReport::from(NurError::TaskNotFound(
+                nur_engine.state().task_call().join(" "),
+            )));
+        }
+    }
+
+    // Ensure we only allow sane calls
+    if nur_engine.state().has_task_call() && parsed_nur_args.run_commands.is_some() {
+        return Err(miette::ErrReport::from(NurError::InvalidNurCall(
+            String::from("task call"),
+            String::from("--commands/-c"),
+        )));
+    }
+    if nur_engine.state().has_task_call() && parsed_nur_args.enter_shell {
+        return Err(miette::ErrReport::from(NurError::InvalidNurCall(
+            String::from("task call"),
+            String::from("--enter-shell"),
+        )));
+    }
+    if parsed_nur_args.run_commands.is_some() && parsed_nur_args.enter_shell {
+        return Err(miette::ErrReport::from(NurError::InvalidNurCall(
+            String::from("--commands/-c"),
+            String::from("--enter-shell"),
+        )));
+    }
+    if nur_engine.state().has_task_call() && nur_engine.state().task_name().is_none() {
+        return Err(miette::ErrReport::from(NurError::TaskNotFound(
+            nur_engine.state().task_call().join(" "),
+        )));
+    }
+
+    // Validate the env vars declared by the task before running anything
+    if nur_engine.state().has_task_call() {
+        nur_engine.prepare_task_env()?;
+    }
+
+    // Run in the directory declared by the task or where nur was invoked (--here)
+    nur_engine.set_task_dir(parsed_nur_args.run_here)?;
+
+    // Ask for missing required task params, only when running interactively
+    if nur_engine.state().has_task_call()
+        && !parsed_nur_args.attach_stdin
+        && io::stdin().is_terminal()
+    {
+        nur_engine.prompt_missing_task_args()?;
+    }
+
+    // Ask for confirmation of tasks requiring it (like deployments or dropping databases)
+    if nur_engine.state().has_task_call() {
+        nur_engine.confirm_task(parsed_nur_args.assume_yes)?;
+    }
+
+    // Check the requested report before running anything
+    let report = match &parsed_nur_args.report {
+        Some(report) => Some(Report::parse(&report.item, nur_engine.state().run_path())?),
+        None => None,
+    };
+
+    // Prepare input data - if requested
+    let input = if parsed_nur_args.attach_stdin {
+        PipelineData::ByteStream(ByteStream::stdin(Span::unknown())?, None)
+    } else {
+        PipelineData::empty()
+    };
+
+    // Execute the task
+    let exit_code: i32;
+    let run_command = if parsed_nur_args.run_commands.is_some() {
+        parsed_nur_args.run_commands.clone().unwrap().item
+    } else {
+        nur_engine.state().task_call().join(" ")
+    };
+    // Mask secrets in all output, the interactive shell needs a real terminal
+    let secrets = secret_values(&mut nur_engine)?;
+    // Debug output is printed after secrets are known, so those can be masked
+    #[cfg(feature = "debug")]
+    if parsed_nur_args.debug_output {
+        eprintln!("run path: {:?}", nur_engine.state().run_path());
+        eprintln!("project path: {:?}", nur_engine.state().project_path());
+        eprintln!();
+        eprintln!(
+            "nur args: {}",
+            mask_secrets(&format!("{:?}", parsed_nur_args), &secrets)
+        );
+        eprintln!(
+            "task call: {}",
+            mask_secrets(&format!("{:?}", nur_engine.state().task_call()), &secrets)
+        );
+        eprintln!();
+        eprintln!("nur config dir: {:?}", nur_engine.state().config_dir());
+        eprintln!(
+            "nur lib path (scripts/): {:?}",
+            nur_engine.state().lib_dir_path()
+        );
+        eprintln!("nur env path (env.nu): {:?}", nur_engine.state().env_path());
+        eprintln!(
+            "nur config path (config.nu): {:?}",
+            nur_engine.state().config_path()
+        );
+        eprintln!("nur history path: {:?}", nur_engine.state().history_path());
+        eprintln!();
+        eprintln!("nurfile path: {:?}", nur_engine.state().nurfile_path());
+        eprintln!(
+            "nurfile local path: {:?}",
+            nur_engine.state().local_nurfile_path()
+        );
+        eprintln!();
+        eprintln!(
+            "full command call: {}",
+            mask_secrets(&run_command, &secrets)
+        );
+    }
+
+    let output_masking = if parsed_nur_args.enter_shell {
+        None
+    } else {
+        OutputMasking::start(&secrets)?
+    };
+    // Capture stderr for reports (needs to be started after masking, will get masked output)
+    let stderr_buffer = SharedBuffer::new();
+    let stderr_tee = if report.is_some() && !parsed_nur_args.enter_shell {
+        Some(OutputTee::start(
+            OutputStream::Stderr,
+            Box::new(stderr_buffer.clone()),
+        )?)
+    } else {
+        None
+    };
+    // Write the task output to a log file (gets unmasked output, secrets are masked while writing)
+    let task_log = if parsed_nur_args.log_output && !parsed_nur_args.enter_shell {
+        let log_task_name = if nur_engine.state().has_task_call() {
+            nur_engine.get_short_task_name()
+        } else {
+            String::from("commands")
+        };
+        Some(TaskLog::start(
+            nur_engine.state().logs_dir(),
+            &log_task_name,
+            &secrets,
+        )?)
+    } else {
+        None
+    };
+    // Emit events for the task, its sub tasks and spawned external commands
+    let event_subject = if nur_engine.state().has_task_call() {
+        json!({
+            "task": nur_engine.get_short_task_name(),
+            "args": nur_engine.state().task_args(),
+        })
+    } else {
+        json!({"command": run_command})
+    };
+    // Collect the results of sub tasks for the report
+    let sub_task_results = report.as_ref().map(|_| TaskResults::new());
+    if events.is_some() || sub_task_results.is_some() {
+        track_calls(
+            nur_engine.engine_state(),
+            events.clone(),
+            sub_task_results.clone(),
+        )?;
+    }
+    if let Some(events) = &events {
+        events.set_secrets(&secrets);
+        events.emit("task_started", event_subject.clone());
+    }
+    let started_at = Instant::now();
+    if parsed_nur_args.enter_shell {
+        exit_code = match nur_engine.run_repl() {
+            Ok(_) => 0,
+            Err(_) => 1,
+        }
+    } else if parsed_nur_args.quiet_execution {
+        exit_code = nur_engine.eval_and_print(&run_command, input)?;
+
+        #[cfg(feature = "debug")]
+        if parsed_nur_args.debug_output {
+            println!("Exit code {:?}", exit_code);
+        }
+    } else {
+        let group_title = if parsed_nur_args.run_commands.is_some() {
+            format!("nur -c {}", run_command)
+        } else {
+            format!("nur {}", nur_engine.get_short_task_name())
+        };
+        if let Some(ci_provider) = ci_provider {
+            println!("{}", ci_provider.group_start(&group_title));
+        } else {
+            println!("nur version {}", env!("CARGO_PKG_VERSION"));
+            println!(
+                "Project path: {}",
+                nur_engine.state().project_path().to_str().unwrap()
+            );
+            if parsed_nur_args.run_commands.is_some() {
+                println!("Running command: {}", run_command);
+            } else {
+                println!("Executing task: {}", nur_engine.get_short_task_name());
+            }
+            println!();
+        }
+        exit_code = nur_engine.eval_and_print(&run_command, input)?;
+        if let Some(ci_provider) = ci_provider {
+            let group_end = ci_provider.group_end(&group_title);
+            if !group_end.is_empty() {
+                println!("{}", group_end);
+            }
+        }
+        #[cfg(feature = "debug")]
+        if parsed_nur_args.debug_output {
+            println!("Exit code {:?}", exit_code);
+        }
+        if exit_code == 0 {
+            println!(
+                "{}Task execution successful{}",
+                if use_color {
+                    Color::Green.prefix().to_string()
+                } else {
+                    String::from("")
+                },
+                if use_color {
+                    Color::Green.suffix().to_string()
+                } else {
+                    String::from("")
+                },
+            );
+        } else {
+            println!(
+                "{}Task execution failed (exit code: {}){}",
+                if use_color {
+                    Color::Red.prefix().to_string()
+                } else {
+                    String::from("")
+                },
+                exit_code,
+                if use_color {
+                    Color::Red.suffix().to_string()
+                } else {
+                    String::from("")
+                },
+            );
+        }
+    }
+
+    let log_path = task_log.map(|task_log| task_log.finish());
+    drop(stderr_tee);
+    drop(output_masking);
+
+    if let Some(log_path) = log_path {
+        if exit_code != 0 {
+            eprintln!("Task output was written to {}", log_path.display());
+        }
+    }
+
+    if let Some(events) = &events {
+        let mut event_data = event_subject;
+        event_data["exit_code"] = json!(exit_code);
+        event_data["duration_ms"] = json!(started_at.elapsed().as_millis() as u64);
+        events.emit("task_finished", event_data);
+    }
+
+    // Write the report of the task results
+    if let Some(report) = report {
+        let task_result = TaskResult {
+            name: if nur_engine.state().has_task_call() {
+                nur_engine.get_short_task_name()
+            } else {
+                run_command.clone()
+            },
+            exit_code,
+            duration: started_at.elapsed(),
+            stderr: mask_secrets(&stderr_buffer.to_string_lossy(), &secrets),
+        };
+        let project_name = nur_engine
+            .state()
+            .project_path()
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // The executed task is followed by all of its sub tasks
+        let mut task_results = vec![task_result];
+        for mut sub_task_result in sub_task_results
+            .map(|results| results.take())
+            .unwrap_or_default()
+        {
+            sub_task_result.stderr = mask_secrets(&sub_task_result.stderr, &secrets);
+            task_results.push(sub_task_result);
+        }
+        if let Err(err) = report.write(&project_name, task_results[0].duration, &task_results) {
+            eprintln!("Could not write report: {}", err);
+        }
+    }
+
+    // Record task runs in history
+    if nur_engine.state().has_task_call() {
+        if let Err(err) = record_task_run(
+            nur_engine.state(),
+            exit_code,
+            started_at.elapsed(),
+            &secrets,
+        ) {
+            eprintln!("Could not record task run in history: {}", err);
+        }
+    }
+
+    Ok(ExitCode::from(exit_code as u8))
+}
@@ -2,6 +2,7 @@ mod nur;
 
 use nu_protocol::engine::{EngineState, StateWorkingSet};
 pub(crate) use nur::Nur;
+pub(crate) use nur::OuterNurCall;
 
 pub(crate) fn create_nu_context(mut engine_state: EngineState) -> EngineState {
     // Custom additions only used in cli, normally registered in nu main() as "custom additions"
@@ -23,7 +24,7 @@ pub(crate) fn create_nur_context(mut engine_state: EngineState) -> EngineState {
     // Add nur own commands
     let delta = {
         let mut working_set = StateWorkingSet::new(&engine_state);
-        working_set.add_decl(Box::new(nur::Nur));
+        working_set.add_decl(Box::new(nur::Nur::default()));
         working_set.render()
     };
 
@@ -1,11 +1,66 @@
-use nu_engine::get_full_help;
-use nu_protocol::engine::{Command, EngineState, Stack};
+use crate::args::is_safe_taskname;
+use crate::ci::CiProvider;
+use crate::confirm::confirm_task_run;
+use crate::errors::NurError;
+use crate::names::NUR_NAME;
+use crate::task_dir::resolve_task_dir;
+use crate::task_env::apply_task_env;
+use nu_engine::{get_full_help, CallExt};
+use nu_parser::{escape_for_script_arg, parse};
+use nu_protocol::ast::{Block, Expr};
+use nu_protocol::engine::{Call, Command, EngineState, Stack, StateWorkingSet};
 use nu_protocol::{
-    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, SyntaxShape, Value,
+    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, Span, SyntaxShape,
+    Value,
 };
+use std::path::PathBuf;
 
-#[derive(Clone)]
-pub(crate) struct Nur;
+// Flags only making sense when starting nur, not when calling a task from another task
+const TOP_LEVEL_ONLY_FLAGS: &[&str] = &[
+    "version",
+    "list",
+    "ci",
+    "no-ci",
+    "commands",
+    "completions",
+    "check",
+    "again",
+    "history",
+    "failed",
+    "fmt",
+    "init",
+    "events",
+    "report",
+    "log",
+    "profile-startup",
+    "here",
+    "use-nu-config",
+    "from",
+    "force",
+    "lsp",
+    "enter-shell",
+];
+
+/// State of the nur call started from the command line, needed when tasks call other tasks
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OuterNurCall {
+    pub project_path: PathBuf,
+    pub quiet: bool,
+    pub assume_yes: bool,
+    pub run_here: bool,
+    pub ci_provider: Option<CiProvider>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Nur {
+    outer_call: OuterNurCall,
+}
+
+impl Nur {
+    pub(crate) fn with_outer_call(outer_call: OuterNurCall) -> Nur {
+        Nur { outer_call }
+    }
+}
 
 impl Command for Nur {
     fn name(&self) -> &str {
@@ -17,7 +72,7 @@ impl Command for Nur {
 
         signature = signature
             .description("nur - a taskrunner based on nu shell.")
-            .switch("version", "Output version number and exit", Some('v'))
+            .switch("version", "Output version number and compiled in command sets and exit", Some('v'))
             .switch("list", "List available tasks and then just exit", Some('l'))
             .switch(
                 "quiet",
@@ -25,12 +80,98 @@ impl Command for Nur {
                 Some('q'),
             )
             .switch("stdin", "Attach stdin to called nur task", None)
+            .switch(
+                "ci",
+                "Format output for CI logs (grouped output, error annotations, no colours), enabled automatically in CI",
+                None,
+            )
+            .switch(
+                "no-ci",
+                "Do not detect running in CI, output is formatted like in a terminal",
+                None,
+            )
+            .switch(
+                "here",
+                "Run the task in the current directory instead of the project directory",
+                None,
+            )
+            .switch(
+                "use-nu-config",
+                "Also load your nu env.nu and config.nu before the .nur ones",
+                None,
+            )
+            .switch(
+                "yes",
+                "Do not ask for confirmation of tasks requiring it",
+                Some('y'),
+            )
             .named(
                 "commands",
                 SyntaxShape::String,
                 "Run the given commands after nurfiles have been loaded",
                 Some('c'),
             )
+            .named(
+                "completions",
+                SyntaxShape::String,
+                "Output the shell completion script for bash, zsh, fish, nu or powershell",
+                None,
+            )
+            .switch(
+                "check",
+                "Check nurfiles, env.nu, config.nu and .nur/scripts/ for problems and exit (only check formatting when used with --fmt)",
+                None,
+            )
+            .switch(
+                "again",
+                "Run the last task call again, including its arguments (same as \"nur !!\")",
+                None,
+            )
+            .switch(
+                "history",
+                "Show the history of task runs, pass a task name to only show runs of this task",
+                None,
+            )
+            .switch("failed", "Only show failed task runs when using --history", None)
+            .switch(
+                "fmt",
+                "Format nurfile, nurfile.local and .nur/*.nu and exit",
+                None,
+            )
+            .switch(
+                "init",
+                "Create a new nurfile and .nur/ config dir in the current directory",
+                None,
+            )
+            .switch(
+                "log",
+                "Also write the task output to .nur/logs/<task>-<timestamp>.log",
+                None,
+            )
+            .named(
+                "events",
+                SyntaxShape::String,
+                "Write machine readable events of the execution, jsonl to stderr or jsonl=<path> to a file",
+                None,
+            )
+            .named(
+                "report",
+                SyntaxShape::String,
+                "Write a report of the task results, like junit=<path> for a JUnit XML file",
+                None,
+            )
+            .named(
+                "from",
+                SyntaxShape::String,
+                "Generate the nurfile for --init from just, make, npm, task, b5, lets, tusk or toolkit",
+                None,
+            )
+            .switch("force", "Overwrite an existing nurfile when using --init", None)
+            .switch(
+                "profile-startup",
+                "Show the time spent loading the engine, env.nu, config.nu and nurfiles",
+                None,
+            )
             .switch(
                 "enter-shell",
                 "Enter a nu REPL shell after the nurfiles have been loaded (use only for debugging)",
@@ -48,6 +189,15 @@ impl Command for Nur {
             )
             .category(Category::Default);
 
+        #[cfg(feature = "lsp")]
+        {
+            signature = signature.switch(
+                "lsp",
+                "Start the language server for nurfiles (LSP over stdio)",
+                None,
+            );
+        }
+
         #[cfg(feature = "debug")]
         {
             signature = signature.switch("debug", "Show debug details", Some('d'));
@@ -64,13 +214,28 @@ impl Command for Nur {
         &self,
         engine_state: &EngineState,
         stack: &mut Stack,
-        call: &nu_protocol::engine::Call,
-        _input: PipelineData,
+        call: &Call,
+        input: PipelineData,
     ) -> Result<PipelineData, ShellError> {
-        Ok(Value::string(get_full_help(&Nur, engine_state, stack), call.head).into_pipeline_data())
+        // Tasks calling "nur <task>" run the task in the same engine, everything else shows the help
+        let task_name: Option<String> = call.opt(engine_state, stack, 0)?;
+        match task_name {
+            Some(task_name) if !call.has_flag(engine_state, stack, "help")? => run_nested_task(
+                &self.outer_call,
+                engine_state,
+                stack,
+                call,
+                task_name,
+                input,
+            ),
+            _ => Ok(
+                Value::string(get_full_help(self, engine_state, stack), call.head)
+                    .into_pipeline_data(),
+            ),
+        }
     }
 
-    fn examples(&self) -> Vec<Example> {
+    fn examples(&self) -> Vec<Example<'_>> {
         vec![
             Example {
                 description: "Execute a task",
@@ -82,6 +247,282 @@ impl Command for Nur {
                 example: "nur --list",
                 result: None,
             },
+            Example {
+                description: "Create a nurfile wrapping the tasks of an existing Makefile",
+                example: "nur --init --from make",
+                result: None,
+            },
         ]
     }
 }
+
+/// Declaration of the task called by the parsed task call
+fn called_task<'a>(engine_state: &'a EngineState, block: &Block) -> Option<&'a dyn Command> {
+    let element = block.pipelines.first()?.elements.first()?;
+    match &element.expr.expr {
+        Expr::Call(call) => Some(engine_state.get_decl(call.decl_id)),
+        _ => None,
+    }
+}
+
+fn nested_task_error(err: NurError, span: Span) -> ShellError {
+    ShellError::GenericError {
+        error: err.to_string(),
+        msg: String::from("task could not be run"),
+        span: Some(span),
+        help: None,
+        inner: vec![],
+    }
+}
+
+/// Run the task using the already loaded task definitions instead of starting a new nur process
+fn run_nested_task(
+    outer_call: &OuterNurCall,
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    call: &Call,
+    task_name: String,
+    input: PipelineData,
+) -> Result<PipelineData, ShellError> {
+    for flag_name in TOP_LEVEL_ONLY_FLAGS {
+        if let Some(span) = call.get_flag_span(stack, flag_name) {
+            return Err(ShellError::GenericError {
+                error: format!(
+                    "--{} can not be used when calling a task from a task",
+                    flag_name
+                ),
+                msg: String::from("only supported when running nur directly"),
+                span: Some(span),
+                help: None,
+                inner: vec![],
+            });
+        }
+    }
+
+    let name_span = call.positional_nth(stack, 0).map(|expr| expr.span);
+    if !is_safe_taskname(&task_name)
+        || engine_state
+            .find_decl(format!("{} {}", NUR_NAME, task_name).as_bytes(), &[])
+            .is_none()
+    {
+        return Err(ShellError::GenericError {
+            error: format!("Task '{}' not found", task_name),
+            msg: String::from("unknown task"),
+            span: name_span.or(Some(call.head)),
+            help: Some(String::from("Use nur --list to show all available tasks")),
+            inner: vec![],
+        });
+    }
+
+    // Sub task names and arguments are resolved like on the command line
+    let task_args: Vec<String> = call.rest(engine_state, stack, 1)?;
+    let mut task_call = vec![String::from(NUR_NAME), task_name];
+    task_call.extend(task_args.iter().map(|arg| escape_for_script_arg(arg)));
+    let task_call = task_call.join(" ");
+
+    // Like for the outer task, CI logs get a collapsible group for the task instead
+    let is_announced = !outer_call.quiet && !call.has_flag(engine_state, stack, "quiet")?;
+    let ci_group = outer_call.ci_provider.filter(|_| is_announced);
+    match ci_group {
+        Some(ci_provider) => println!("{}", ci_provider.group_start(&task_call)),
+        None if is_announced => {
+            println!("Executing task: {}", &task_call[NUR_NAME.len() + 1..])
+        }
+        None => {}
+    }
+    let input = if call.has_flag(engine_state, stack, "stdin")? {
+        input
+    } else {
+        PipelineData::empty()
+    };
+
+    let mut engine_state = engine_state.clone();
+    let block = {
+        let mut working_set = StateWorkingSet::new(&engine_state);
+        let block = parse(&mut working_set, None, task_call.as_bytes(), false);
+        if let Some(err) = working_set.parse_errors.first() {
+            return Err(ShellError::GenericError {
+                error: format!("Could not call task: {}", task_call),
+                msg: err.to_string(),
+                span: Some(call.head),
+                help: None,
+                inner: vec![],
+            });
+        }
+        engine_state.merge_delta(working_set.render())?;
+        block
+    };
+
+    // Prepare the called task like the task called by nur itself
+    let mut task_dir: Option<PathBuf> = None;
+    if let Some(command) = called_task(&engine_state, &block) {
+        let short_task_name = command
+            .name()
+            .strip_prefix(&format!("{} ", NUR_NAME))
+            .unwrap_or(command.name())
+            .to_string();
+        let extra_description = command.extra_description().to_string();
+
+        apply_task_env(
+            &mut engine_state,
+            stack,
+            &short_task_name,
+            &extra_description,
+        )
+        .map_err(|err| nested_task_error(err, call.head))?;
+        // Like the outer task, --here keeps the current directory
+        if !outer_call.run_here {
+            task_dir = resolve_task_dir(
+                &outer_call.project_path,
+                &short_task_name,
+                &extra_description,
+            )
+            .map_err(|err| nested_task_error(err, call.head))?;
+        }
+        let assume_yes = outer_call.assume_yes || call.has_flag(&engine_state, stack, "yes")?;
+        confirm_task_run(
+            &short_task_name,
+            &outer_call.project_path,
+            &extra_description,
+            assume_yes,
+        )
+        .map_err(|err| nested_task_error(err, call.head))?;
+    }
+
+    // The declared directory only applies while the task is running
+    let caller_dir = stack.get_env_var(&engine_state, "PWD").cloned();
+    if let Some(task_dir) = &task_dir {
+        stack.add_env_var(
+            String::from("PWD"),
+            Value::string(task_dir.to_string_lossy(), call.head),
+        );
+    }
+    let result = nu_engine::get_eval_block(&engine_state)(&engine_state, stack, &block, input);
+    if let (Some(_), Some(caller_dir)) = (&task_dir, caller_dir) {
+        stack.add_env_var(String::from("PWD"), caller_dir);
+    }
+    if let Some(ci_provider) = ci_group {
+        let group_end = ci_provider.group_end(&task_call);
+        if !group_end.is_empty() {
+            println!("{}", group_end);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{init_engine_state, NurEngine};
+    use crate::names::{NUR_FILE, NUR_LOCAL_FILE};
+    use crate::state::NurState;
+    use std::fs;
+    use std::io::{self, IsTerminal};
+    use tempfile::{tempdir, TempDir};
+
+    fn _prepare_nur_engine(nurfile: &str, local_nurfile: &str) -> (TempDir, NurEngine) {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), nurfile).unwrap();
+        fs::write(temp_dir.path().join(NUR_LOCAL_FILE), local_nurfile).unwrap();
+
+        let nur_state =
+            NurState::new(temp_dir.path().to_path_buf(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        (temp_dir, nur_engine)
+    }
+
+    #[test]
+    fn test_nested_task_call() {
+        // The called task is only defined after the nurfile was parsed
+        let (_temp_dir, mut nur_engine) = _prepare_nur_engine(
+            "def \"nur all\" [] { nur --quiet later 3 }",
+            "def \"nur later\" [n: int] { $n * 2 }",
+        );
+
+        let output = nur_engine.run_task(["all"]).unwrap();
+        assert_eq!(output.to_string_lossy(), "6");
+    }
+
+    #[test]
+    fn test_nested_task_call_errors() {
+        let (_temp_dir, mut nur_engine) = _prepare_nur_engine(
+            "def \"nur missing\" [] { nur --quiet unknown }\ndef \"nur list\" [] { nur --list other }",
+            "",
+        );
+
+        assert!(nur_engine.run_task(["missing"]).is_err());
+        assert!(nur_engine.run_task(["list"]).is_err());
+    }
+
+    #[test]
+    fn test_nested_task_call_prepares_task() {
+        let (temp_dir, mut nur_engine) = _prepare_nur_engine(
+            "def \"nur info-all\" [] { let info = (nur --quiet info); $info | append ($env.PWD | path basename) | str join \",\" }\n\
+             def \"nur required\" [] { nur --quiet needs-token }\n\
+             def \"nur deploy-all\" [] { nur --quiet deploy }\n\
+             def \"nur deploy-yes\" [] { nur --quiet --yes deploy }",
+            "# Show info\n#\n# env: NUR_TEST_REGION=eu-west-1\n# dir: sub\n\
+             def \"nur info\" [] { [$env.NUR_TEST_REGION ($env.PWD | path basename)] }\n\
+             # Needs a token\n#\n# env: NUR_TEST_MISSING_TOKEN\n\
+             def \"nur needs-token\" [] { }\n\
+             # Deploy\n#\n# confirm\n\
+             def \"nur deploy\" [] { \"deployed\" }",
+        );
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+
+        // Env defaults and the task dir only apply to the called task
+        let project_name = temp_dir.path().file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            nur_engine.run_task(["info-all"]).unwrap().to_string_lossy(),
+            format!("eu-west-1,sub,{}", project_name)
+        );
+        assert!(nur_engine.run_task(["required"]).is_err());
+
+        assert_eq!(
+            nur_engine
+                .run_task(["deploy-yes"])
+                .unwrap()
+                .to_string_lossy(),
+            "deployed"
+        );
+        if !io::stdin().is_terminal() {
+            assert!(nur_engine.run_task(["deploy-all"]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_quiet_nur_call_does_not_leak_into_env() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(NUR_FILE),
+            "def \"nur outer\" [] { nur --stdin inner }\ndef \"nur inner\" [] { ^env | lines | where $it starts-with NUR_QUIET= | length }",
+        )
+        .unwrap();
+
+        let nur_state = NurState::new(
+            temp_dir.path().to_path_buf(),
+            vec![
+                String::from("nur"),
+                String::from("--quiet"),
+                String::from("outer"),
+            ],
+        )
+        .unwrap();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+        assert!(nur_engine.parse_args().unwrap().quiet_execution);
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        assert_eq!(
+            nur_engine.run_task(["outer"]).unwrap().to_string_lossy(),
+            "0"
+        );
+    }
+}
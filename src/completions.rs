@@ -0,0 +1,728 @@
+use crate::cache::TaskCache;
+use crate::commands::Nur;
+use crate::engine::NurEngine;
+use crate::errors::{NurError, NurResult};
+use crate::names::NUR_NAME;
+use crate::nurify::NURIFY_RUNNERS;
+use crate::state::NurState;
+use crate::task_dir::resolve_task_dir;
+use nu_protocol::engine::Command;
+use nu_protocol::{DeclId, Signature, SyntaxShape, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub(crate) const COMPLETION_SHELLS: [&str; 5] = ["bash", "zsh", "fish", "nu", "powershell"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Completion {
+    pub value: String,
+    pub description: String,
+}
+
+impl Completion {
+    fn new<V: Into<String>, D: Into<String>>(value: V, description: D) -> Self {
+        Completion {
+            value: value.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// Source of the task details needed for completions
+trait CompletionContext {
+    fn task_names(&self) -> Vec<String>;
+    fn task_signature(&self, task_name: &str) -> Option<Signature>;
+    fn run_path(&self) -> &Path;
+    /// Directory the task runs in, paths given to the task are relative to it
+    fn task_dir(&self, task_name: &str, run_here: bool) -> PathBuf;
+    /// Run the custom completer, None if this is not possible (completion needs the engine)
+    fn custom_completions(&mut self, decl_id: DeclId, partial: &str) -> Option<Vec<Completion>>;
+}
+
+impl CompletionContext for NurEngine {
+    fn task_names(&self) -> Vec<String> {
+        self.list_tasks()
+    }
+
+    fn task_signature(&self, task_name: &str) -> Option<Signature> {
+        self.get_def(format!("{} {}", NUR_NAME, task_name))
+            .map(|decl| decl.signature())
+    }
+
+    fn run_path(&self) -> &Path {
+        &self.state.run_path
+    }
+
+    fn task_dir(&self, task_name: &str, run_here: bool) -> PathBuf {
+        self.task_working_dir_of(task_name, run_here)
+    }
+
+    fn custom_completions(&mut self, decl_id: DeclId, partial: &str) -> Option<Vec<Completion>> {
+        let completer_name = self.engine_state.get_decl(decl_id).name().to_string();
+
+        Some(completer_completions(self, completer_name, partial))
+    }
+}
+
+struct CachedCompletionContext<'a> {
+    task_cache: &'a TaskCache,
+    nur_state: &'a NurState,
+}
+
+impl CompletionContext for CachedCompletionContext<'_> {
+    fn task_names(&self) -> Vec<String> {
+        self.task_cache.task_names()
+    }
+
+    fn task_signature(&self, task_name: &str) -> Option<Signature> {
+        self.task_cache.task_signature(task_name).cloned()
+    }
+
+    fn run_path(&self) -> &Path {
+        &self.nur_state.run_path
+    }
+
+    fn task_dir(&self, task_name: &str, run_here: bool) -> PathBuf {
+        if run_here {
+            return self.nur_state.run_path.clone();
+        }
+
+        // Tasks run in the project path unless they declare a directory
+        let project_path = &self.nur_state.project_path;
+        self.task_cache
+            .task_signature(task_name)
+            .and_then(|signature| {
+                resolve_task_dir(project_path, task_name, &signature.extra_description)
+                    .ok()
+                    .flatten()
+            })
+            .unwrap_or_else(|| project_path.clone())
+    }
+
+    fn custom_completions(&mut self, _decl_id: DeclId, _partial: &str) -> Option<Vec<Completion>> {
+        None
+    }
+}
+
+/// Split a (partial) command line into complete words and the word currently being typed
+fn split_commandline(line: &str) -> (Vec<String>, String) {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' || c == '`' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    (words, current)
+}
+
+fn flag_completions(signature: &Signature, partial: &str, used: &[String]) -> Vec<Completion> {
+    let mut completions = Vec::new();
+
+    for flag in &signature.named {
+        let long = format!("--{}", flag.long);
+        if !used.contains(&long) && long.starts_with(partial) {
+            completions.push(Completion::new(long, flag.desc.clone()));
+        }
+        if let Some(short) = flag.short {
+            let short = format!("-{}", short);
+            if partial.len() == 2 && short == partial {
+                completions.push(Completion::new(short, flag.desc.clone()));
+            }
+        }
+    }
+
+    completions
+}
+
+fn flag_takes_value(signature: &Signature, word: &str) -> Option<SyntaxShape> {
+    let flag = if let Some(long) = word.strip_prefix("--") {
+        signature.named.iter().find(|f| f.long == long)
+    } else if let Some(short) = word.strip_prefix('-') {
+        let mut chars = short.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => signature.named.iter().find(|f| f.short == Some(c)),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    flag.and_then(|f| f.arg.clone())
+}
+
+fn positional_shape(signature: &Signature, index: usize) -> Option<SyntaxShape> {
+    signature
+        .required_positional
+        .iter()
+        .chain(signature.optional_positional.iter())
+        .nth(index)
+        .or(signature.rest_positional.as_ref())
+        .map(|arg| arg.shape.clone())
+}
+
+fn path_completions(run_path: &Path, partial: &str, only_dirs: bool) -> Vec<Completion> {
+    let (dir_part, file_part) = match partial.rfind('/') {
+        Some(index) => (&partial[..=index], &partial[index + 1..]),
+        None => ("", partial),
+    };
+    let search_path = run_path.join(dir_part);
+
+    let Ok(entries) = fs::read_dir(search_path) else {
+        return vec![];
+    };
+
+    let mut completions: Vec<Completion> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if !name.starts_with(file_part) || (only_dirs && !is_dir) {
+                return None;
+            }
+            if name.starts_with('.') && !file_part.starts_with('.') {
+                return None;
+            }
+            let suffix = if is_dir { "/" } else { "" };
+            Some(Completion::new(
+                format!("{}{}{}", dir_part, name, suffix),
+                "",
+            ))
+        })
+        .collect();
+    completions.sort_by(|a, b| a.value.cmp(&b.value));
+
+    completions
+}
+
+fn completer_completions(
+    nur_engine: &mut NurEngine,
+    completer_name: String,
+    partial: &str,
+) -> Vec<Completion> {
+    let Ok(output) = nur_engine.eval_to_value(completer_name) else {
+        return vec![];
+    };
+
+    let Value::List { vals, .. } = output.value else {
+        return vec![];
+    };
+
+    vals.into_iter()
+        .filter_map(|value| match value {
+            Value::Record { val, .. } => {
+                let value = val.get("value")?.coerce_string().ok()?;
+                let description = val
+                    .get("description")
+                    .and_then(|d| d.coerce_string().ok())
+                    .unwrap_or_default();
+                Some(Completion::new(value, description))
+            }
+            value => value
+                .coerce_string()
+                .ok()
+                .map(|value| Completion::new(value, "")),
+        })
+        .filter(|completion| completion.value.starts_with(partial))
+        .collect()
+}
+
+/// Complete a value of the given shape, paths are completed relative to the base path
+fn value_completions(
+    context: &mut dyn CompletionContext,
+    shape: SyntaxShape,
+    partial: &str,
+    base_path: &Path,
+) -> Option<Vec<Completion>> {
+    let completions = match shape {
+        SyntaxShape::CompleterWrapper(_, decl_id) => {
+            return context.custom_completions(decl_id, partial)
+        }
+        SyntaxShape::Boolean => ["true", "false"]
+            .into_iter()
+            .filter(|value| value.starts_with(partial))
+            .map(|value| Completion::new(value, ""))
+            .collect(),
+        SyntaxShape::Filepath | SyntaxShape::GlobPattern => {
+            path_completions(base_path, partial, false)
+        }
+        SyntaxShape::Directory => path_completions(base_path, partial, true),
+        _ => vec![],
+    };
+
+    Some(completions)
+}
+
+fn task_name_completions(
+    context: &dyn CompletionContext,
+    parent_task: &str,
+    partial: &str,
+) -> Vec<Completion> {
+    let prefix = if parent_task.is_empty() {
+        String::new()
+    } else {
+        format!("{} ", parent_task)
+    };
+
+    let mut completions: Vec<Completion> = Vec::new();
+    for task_name in context.task_names() {
+        let Some(sub_name) = task_name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let next_word = sub_name.split(' ').next().unwrap_or_default();
+        if next_word.is_empty()
+            || !next_word.starts_with(partial)
+            || completions.iter().any(|c| c.value == next_word)
+        {
+            continue;
+        }
+
+        let description = context
+            .task_signature(&format!("{}{}", prefix, next_word))
+            .map(|signature| signature.description)
+            .unwrap_or_default();
+        completions.push(Completion::new(next_word, description));
+    }
+
+    completions
+}
+
+/// Complete the given (partial) nur command line, like "nur some-task --fl"
+///
+/// Completes nur flags, task names, sub tasks, task flags and task parameters (using the
+/// task signature, including custom completers).
+pub(crate) fn complete_commandline(nur_engine: &mut NurEngine, line: &str) -> Vec<Completion> {
+    complete(nur_engine, line).unwrap_or_default()
+}
+
+/// Complete the command line using the cached task signatures, returns None if the engine is
+/// needed (like for custom completers)
+pub(crate) fn complete_commandline_cached(
+    task_cache: &TaskCache,
+    nur_state: &NurState,
+    line: &str,
+) -> Option<Vec<Completion>> {
+    let mut context = CachedCompletionContext {
+        task_cache,
+        nur_state,
+    };
+
+    complete(&mut context, line)
+}
+
+fn complete(context: &mut dyn CompletionContext, line: &str) -> Option<Vec<Completion>> {
+    let (words, partial) = split_commandline(line);
+    let mut words = words.into_iter().skip(1).peekable(); // Ignore own name
+
+    // Consume nur flags
+    let nur_signature = Nur::default().signature();
+    let mut nur_flags = Vec::new();
+    while let Some(word) = words.next_if(|w| w.starts_with('-')) {
+        if let Some(shape) = flag_takes_value(&nur_signature, &word) {
+            if words.next().is_none() {
+                let choices: &[&str] = match word.as_str() {
+                    "--completions" => &COMPLETION_SHELLS,
+                    "--from" => &NURIFY_RUNNERS,
+                    _ => {
+                        // Paths given to nur itself are relative to the current directory
+                        let run_path = context.run_path().to_path_buf();
+                        return value_completions(context, shape, &partial, &run_path);
+                    }
+                };
+                return Some(
+                    choices
+                        .iter()
+                        .filter(|choice| choice.starts_with(partial.as_str()))
+                        .map(|choice| Completion::new(*choice, ""))
+                        .collect(),
+                );
+            }
+        }
+        nur_flags.push(word);
+    }
+
+    let task_words: Vec<String> = words.collect();
+    if task_words.is_empty() {
+        return Some(if partial.starts_with('-') {
+            flag_completions(&nur_signature, &partial, &nur_flags)
+        } else {
+            task_name_completions(context, "", &partial)
+        });
+    }
+
+    // Find the longest matching task name
+    let Some((task_length, signature)) = (1..=task_words.len()).rev().find_map(|length| {
+        context
+            .task_signature(&task_words[..length].join(" "))
+            .map(|signature| (length, signature))
+    }) else {
+        return Some(vec![]);
+    };
+    let task_name = task_words[..task_length].join(" ");
+    let task_args = &task_words[task_length..];
+    let task_dir = context.task_dir(&task_name, nur_flags.iter().any(|flag| flag == "--here"));
+
+    if partial.starts_with('-') {
+        return Some(flag_completions(&signature, &partial, task_args));
+    }
+
+    // Count positional params already given, check whether a flag value is expected
+    let mut positional_index = 0;
+    let mut args_iter = task_args.iter();
+    while let Some(arg) = args_iter.next() {
+        if arg.starts_with('-') {
+            if let Some(shape) = flag_takes_value(&signature, arg) {
+                if args_iter.next().is_none() {
+                    return value_completions(context, shape, &partial, &task_dir);
+                }
+            }
+        } else {
+            positional_index += 1;
+        }
+    }
+
+    let mut completions = if task_args.is_empty() {
+        task_name_completions(context, &task_name, &partial)
+    } else {
+        vec![]
+    };
+    if let Some(shape) = positional_shape(&signature, positional_index) {
+        completions.extend(value_completions(context, shape, &partial, &task_dir)?);
+    }
+
+    Some(completions)
+}
+
+fn nu_extern_flags() -> String {
+    Nur::default()
+        .signature()
+        .named
+        .iter()
+        .map(|flag| {
+            let short = flag.short.map(|s| format!("(-{})", s)).unwrap_or_default();
+            let arg = flag
+                .arg
+                .as_ref()
+                .map(|shape| {
+                    if flag.long == "completions" {
+                        String::from(": string@\"nu-complete nur\"")
+                    } else {
+                        format!(": {}", shape)
+                    }
+                })
+                .unwrap_or_default();
+            format!("  --{}{}{}  # {}\n", flag.long, short, arg, flag.desc)
+        })
+        .collect()
+}
+
+/// Generate the completion script for the given shell
+pub(crate) fn completion_script(shell: &str) -> NurResult<String> {
+    let script = match shell {
+        "bash" => String::from(
+            r#"# nur bash completions, generated by "nur --completions bash"
+_nur_completions()
+{
+    local IFS=$'\n'
+    local line="${COMP_LINE:0:$COMP_POINT}"
+    COMPREPLY=( $( nur --complete "${line}" 2>/dev/null | cut -f1 ) )
+    if [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == */ ]]; then
+        compopt -o nospace
+    fi
+}
+complete -F _nur_completions nur
+"#,
+        ),
+        "zsh" => String::from(
+            r#"#compdef nur
+# nur zsh completions, generated by "nur --completions zsh"
+
+_nur() {
+    local -a completions
+    local completion value description
+    for completion in "${(@f)$(nur --complete "${BUFFER[1,CURSOR]}" 2>/dev/null)}"; do
+        [[ -z "$completion" ]] && continue
+        value="${completion%%$'\t'*}"
+        description="${completion#*$'\t'}"
+        completions+=("${value//:/\\:}:${description}")
+    done
+    _describe 'nur' completions
+}
+
+compdef _nur nur
+"#,
+        ),
+        "fish" => String::from(
+            r#"# nur fish completions, generated by "nur --completions fish"
+function __nur_complete
+    nur --complete (commandline -cp) 2>/dev/null
+end
+complete -c nur -f -a "(__nur_complete)"
+"#,
+        ),
+        "nu" => format!(
+            r#"# nur nu completions, generated by "nur --completions nu"
+def "nu-complete nur" [context: string] {{
+  ^nur --complete $context | lines | parse "{{value}}\t{{description}}"
+}}
+
+# {}
+export extern nur [
+{}  task_name?: string@"nu-complete nur"  # Name of the task to run (you may use sub tasks)
+  ...args: string@"nu-complete nur"  # Parameters for the executed task
+]
+"#,
+            Nur::default().description(),
+            nu_extern_flags(),
+        ),
+        "powershell" => String::from(
+            r#"# nur powershell completions, generated by "nur --completions powershell"
+Register-ArgumentCompleter -Native -CommandName nur -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $text = $commandAst.Extent.Text
+    $length = [Math]::Min($cursorPosition - $commandAst.Extent.StartOffset, $text.Length)
+    $line = $text.Substring(0, $length)
+    if ($cursorPosition -gt $commandAst.Extent.EndOffset) { $line += " " }
+    nur --complete "$line" 2>$null | ForEach-Object {
+        $value, $description = $_ -split "`t", 2
+        if (-not $description) { $description = $value }
+        [System.Management.Automation.CompletionResult]::new($value, $value, 'ParameterValue', $description)
+    }
+}
+"#,
+        ),
+        _ => return Err(NurError::UnsupportedShell(String::from(shell))),
+    };
+
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::NurBuilder;
+    use crate::names::NUR_FILE;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::{tempdir, TempDir};
+
+    fn _values(completions: Vec<Completion>) -> Vec<String> {
+        completions.into_iter().map(|c| c.value).collect()
+    }
+
+    fn _prepare_nur_engine(temp_dir: &TempDir) -> NurEngine {
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile
+            .write_all(
+                br#"
+def "nu-complete envs" [] { ["dev", "prod"] }
+# Deploy the project
+def "nur deploy" [
+    target: string@"nu-complete envs"  # Target environment
+    --dry-run  # Only show what would happen
+    --tag: string  # Tag to deploy
+] {}
+# Deploy the docs
+def "nur deploy docs" [] {}
+def "nur build" [] {}
+"#,
+            )
+            .unwrap();
+
+        NurBuilder::new().run_path(temp_dir.path()).build().unwrap()
+    }
+
+    #[test]
+    fn test_split_commandline() {
+        assert_eq!(
+            split_commandline("nur some-task 'quoted arg' par"),
+            (
+                vec![
+                    String::from("nur"),
+                    String::from("some-task"),
+                    String::from("quoted arg")
+                ],
+                String::from("par")
+            )
+        );
+        assert_eq!(
+            split_commandline("nur some-task "),
+            (
+                vec![String::from("nur"), String::from("some-task")],
+                String::new()
+            )
+        );
+    }
+
+    #[test]
+    fn test_complete_commandline_task_names() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur ")),
+            vec![String::from("build"), String::from("deploy")]
+        );
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur --quiet de")),
+            vec![String::from("deploy")]
+        );
+    }
+
+    #[test]
+    fn test_complete_commandline_nur_flags() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur --qu")),
+            vec![String::from("--quiet")]
+        );
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur --completions f")),
+            vec![String::from("fish")]
+        );
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur --init --from t")),
+            vec![
+                String::from("task"),
+                String::from("tusk"),
+                String::from("toolkit")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_complete_commandline_sub_tasks_and_params() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur deploy ")),
+            vec![
+                String::from("docs"),
+                String::from("dev"),
+                String::from("prod")
+            ]
+        );
+        assert_eq!(
+            _values(complete_commandline(
+                &mut nur_engine,
+                "nur deploy --dry-run p"
+            )),
+            vec![String::from("prod")]
+        );
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur deploy --tag ")),
+            vec![] as Vec<String>
+        );
+    }
+
+    #[test]
+    fn test_complete_commandline_task_flags() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur deploy --")),
+            vec![
+                String::from("--dry-run"),
+                String::from("--tag"),
+                String::from("--help")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_complete_commandline_cached() {
+        let temp_dir = tempdir().unwrap();
+        let nur_engine = _prepare_nur_engine(&temp_dir);
+        let task_cache = TaskCache::from_engine(&nur_engine);
+
+        assert_eq!(
+            complete_commandline_cached(&task_cache, &nur_engine.state, "nur de").map(_values),
+            Some(vec![String::from("deploy")])
+        );
+        assert_eq!(
+            complete_commandline_cached(&task_cache, &nur_engine.state, "nur deploy --d")
+                .map(_values),
+            Some(vec![String::from("--dry-run")])
+        );
+        // Custom completers need the engine
+        assert_eq!(
+            complete_commandline_cached(&task_cache, &nur_engine.state, "nur deploy "),
+            None
+        );
+    }
+
+    #[test]
+    fn test_complete_commandline_paths_in_task_dir() {
+        let temp_dir = tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        File::create(temp_dir.path().join("sub").join("inside.txt")).unwrap();
+        File::create(temp_dir.path().join("outside.txt")).unwrap();
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile
+            .write_all(
+                br#"
+# Show a file
+#
+# dir: sub
+def "nur show" [file: path] {}
+def "nur show-root" [file: path] {}
+"#,
+            )
+            .unwrap();
+        let mut nur_engine = NurBuilder::new().run_path(temp_dir.path()).build().unwrap();
+
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur show i")),
+            vec![String::from("inside.txt")]
+        );
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur show-root o")),
+            vec![String::from("outside.txt")]
+        );
+        // --here runs the task in the current directory
+        assert_eq!(
+            _values(complete_commandline(&mut nur_engine, "nur --here show o")),
+            vec![String::from("outside.txt")]
+        );
+
+        let task_cache = TaskCache::from_engine(&nur_engine);
+        assert_eq!(
+            complete_commandline_cached(&task_cache, &nur_engine.state, "nur show i").map(_values),
+            Some(vec![String::from("inside.txt")])
+        );
+    }
+
+    #[test]
+    fn test_completion_script_rejects_unknown_shell() {
+        assert!(completion_script("bash").is_ok());
+        assert!(matches!(
+            completion_script("cmd"),
+            Err(NurError::UnsupportedShell(_))
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::errors::{NurError, NurResult};
+use crate::task_metadata::TaskMetadata;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+fn is_confirmed(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Ask the question on stderr and read the answer from stdin, anything but "y" or "yes" is a no
+pub(crate) fn ask_confirmation(question: &str) -> io::Result<bool> {
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(is_confirmed(&answer))
+}
+
+/// Ask for confirmation if the task requires it, fails if it was not confirmed (or there is no
+/// terminal to ask on)
+pub(crate) fn confirm_task_run(
+    task_name: &str,
+    project_path: &Path,
+    extra_description: &str,
+    assume_yes: bool,
+) -> NurResult<()> {
+    let Some(reason) = TaskMetadata::parse(extra_description).confirmation_reason else {
+        return Ok(());
+    };
+    if assume_yes {
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(NurError::ConfirmationRequired(String::from(task_name)));
+    }
+    if !reason.is_empty() {
+        eprintln!("{}", reason);
+    }
+    let project_name = project_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| project_path.to_string_lossy().to_string());
+    if !ask_confirmation(&format!(
+        "Run task {} in project {}?",
+        task_name, project_name
+    ))? {
+        return Err(NurError::TaskNotConfirmed(String::from(task_name)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_confirmed() {
+        assert!(is_confirmed("y\n"));
+        assert!(is_confirmed("YES"));
+        assert!(!is_confirmed("\n"));
+        assert!(!is_confirmed("no"));
+    }
+}
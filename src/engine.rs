@@ -1,33 +1,73 @@
 use crate::args::{is_safe_taskname, parse_commandline_args, NurArgs};
-use crate::errors::NurError::EnteredShellError;
+use crate::capture::OutputCapture;
+use crate::ci::CiProvider;
+use crate::commands::{Nur, OuterNurCall};
+use crate::confirm::confirm_task_run;
 use crate::errors::{NurError, NurResult};
 use crate::names::{
-    NUR_ENV_NUR_TASK_CALL, NUR_ENV_NUR_TASK_NAME, NUR_ENV_NUR_VERSION, NUR_ENV_NU_LIB_DIRS,
-    NUR_NAME, NUR_VAR_CONFIG_DIR, NUR_VAR_DEFAULT_LIB_DIR, NUR_VAR_PROJECT_PATH, NUR_VAR_RUN_PATH,
-    NUR_VAR_TASK_NAME,
+    NUR_ENV_NUR_SECRETS_IDENTITY, NUR_ENV_NUR_TASK_CALL, NUR_ENV_NUR_TASK_NAME,
+    NUR_ENV_NUR_VERSION, NUR_ENV_NU_LIB_DIRS, NUR_NAME, NUR_VAR_CONFIG_DIR,
+    NUR_VAR_DEFAULT_LIB_DIR, NUR_VAR_FLAGS, NUR_VAR_IS_TTY, NUR_VAR_NUR_VERSION,
+    NUR_VAR_NU_VERSION, NUR_VAR_PROJECT_PATH, NUR_VAR_RUN_PATH, NUR_VAR_TASK_ARGS,
+    NUR_VAR_TASK_NAME, NUR_VAR_TASK_PATH,
 };
 use crate::nu_version::NU_VERSION;
-use crate::scripts::{get_default_nur_config, get_default_nur_env};
+#[cfg(feature = "plugin")]
+use crate::plugins::{plugins_to_register, read_plugin_list};
+use crate::prompt::prompt_missing_positionals;
+use crate::scripts::{get_default_nur_config, get_default_nur_env, get_nur_shell_setup};
+use crate::secrets::{check_secrets_file_permissions, decrypt_secrets_file, load_secrets_script};
+use crate::shell::run_shell;
 use crate::state::NurState;
-use nu_cli::{evaluate_repl, gather_parent_env_vars};
+use crate::task_dir::resolve_task_dir;
+use crate::task_env::apply_task_env;
+use crate::task_metadata::TaskHelp;
+use nu_cli::gather_parent_env_vars;
+#[cfg(feature = "plugin")]
+use nu_cli::read_plugin_file;
 use nu_engine::get_full_help;
+use nu_parser::escape_for_script_arg;
 use nu_protocol::ast::Block;
 use nu_protocol::engine::{Command, Stack, StateWorkingSet};
 use nu_protocol::{
-    engine::EngineState, record, report_parse_error, report_shell_error, Config, IntoValue,
-    PipelineData, Record, ShellError, Span, Type, Value,
+    engine::EngineState, record, report_parse_error, report_shell_error, Category, Config, DeclId,
+    IntoValue, PipelineData, Record, ShellError, Span, Type, Value,
 };
 use nu_std::load_standard_library;
 use nu_utils::stdout_write_all_and_flush;
+use std::env;
 use std::fs;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// Names of the command sets compiled into nur, depending on the enabled cargo features
+pub(crate) fn compiled_command_sets() -> Vec<&'static str> {
+    let mut command_sets = vec!["lang", "shell"];
+    if cfg!(feature = "extra-commands") {
+        command_sets.push("extra");
+    }
+    command_sets.push("cli");
+    if cfg!(feature = "plugin") {
+        command_sets.push("plugin");
+    }
+    if cfg!(feature = "explore") {
+        command_sets.push("explore");
+    }
+    command_sets.push("nur");
+
+    command_sets
+}
+
 pub(crate) fn init_engine_state<P: AsRef<Path>>(project_path: P) -> NurResult<EngineState> {
     let engine_state = nu_cmd_lang::create_default_context();
     let engine_state = nu_command::add_shell_command_context(engine_state);
+    #[cfg(feature = "extra-commands")]
     let engine_state = nu_cmd_extra::add_extra_command_context(engine_state);
     let engine_state = nu_cli::add_cli_context(engine_state);
+    #[cfg(feature = "plugin")]
+    let engine_state = nu_cmd_plugin::add_plugin_command_context(engine_state);
+    #[cfg(feature = "explore")]
     let engine_state = nu_explore::add_explore_context(engine_state);
     let engine_state = crate::commands::create_nu_context(engine_state);
     let engine_state = crate::commands::create_nur_context(engine_state);
@@ -67,14 +107,36 @@ pub(crate) fn init_engine_state<P: AsRef<Path>>(project_path: P) -> NurResult<En
     Ok(engine_state)
 }
 
+/// The nu engine with all nur additions, see [`crate::NurBuilder`] for setting it up
+///
+/// The nu engine state and stack may be changed (for example to add commands or variables)
+/// using the accessors, the [`NurState`] is read only.
 #[derive(Clone)]
-pub(crate) struct NurEngine {
+pub struct NurEngine {
     pub(crate) engine_state: EngineState,
     pub(crate) stack: Stack,
 
     pub(crate) state: NurState,
 }
 
+/// Result of a task run through [`NurEngine::run_task`]
+#[derive(Clone, Debug)]
+pub struct NurTaskOutput {
+    pub exit_code: i32,
+    /// Value returned by the task
+    pub value: Value,
+    /// Output printed by the task (including external commands), only captured on unix
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl NurTaskOutput {
+    /// Render the returned value as a string (lists and tables are joined by newlines)
+    pub fn to_string_lossy(&self) -> String {
+        self.value.to_expanded_string("\n", &Config::default())
+    }
+}
+
 impl NurEngine {
     pub(crate) fn new(engine_state: EngineState, nur_state: NurState) -> NurResult<NurEngine> {
         let mut nur_engine = NurEngine {
@@ -85,10 +147,49 @@ impl NurEngine {
         };
 
         nur_engine._apply_nur_state()?;
+        nur_engine._register_nur_command()?;
 
         Ok(nur_engine)
     }
 
+    pub fn engine_state(&self) -> &EngineState {
+        &self.engine_state
+    }
+
+    pub fn engine_state_mut(&mut self) -> &mut EngineState {
+        &mut self.engine_state
+    }
+
+    pub fn stack(&self) -> &Stack {
+        &self.stack
+    }
+
+    pub fn stack_mut(&mut self) -> &mut Stack {
+        &mut self.stack
+    }
+
+    pub fn state(&self) -> &NurState {
+        &self.state
+    }
+
+    /// Register the nur command again, so tasks calling other tasks know the outer nur call
+    fn _register_nur_command(&mut self) -> NurResult<()> {
+        let nur_args = self.state.nur_args.as_ref();
+        let outer_call = OuterNurCall {
+            project_path: self.state.project_path.clone(),
+            quiet: nur_args.is_some_and(|nur_args| nur_args.quiet_execution),
+            assume_yes: nur_args.is_some_and(|nur_args| nur_args.assume_yes),
+            run_here: nur_args.is_some_and(|nur_args| nur_args.run_here),
+            ci_provider: self.state.ci_provider,
+        };
+
+        let mut working_set = StateWorkingSet::new(&self.engine_state);
+        working_set.add_decl(Box::new(Nur::with_outer_call(outer_call)));
+        self.engine_state.merge_delta(working_set.render())?;
+
+        Ok(())
+    }
+
     fn _apply_nur_state(&mut self) -> NurResult<()> {
         // Set default scripts path
         self.engine_state.add_env_var(
@@ -113,6 +214,22 @@ impl NurEngine {
         self.engine_state.generate_nu_constant();
 
         // Set up the $nur constant record (like $nu)
+        let nur_record = self._nur_record();
+        let mut working_set = StateWorkingSet::new(&self.engine_state);
+        let nur_var_id = working_set.add_variable(
+            NUR_NAME.as_bytes().into(),
+            Span::unknown(),
+            Type::Any,
+            false,
+        );
+        self.stack
+            .add_var(nur_var_id, Value::record(nur_record, Span::unknown()));
+        self.engine_state.merge_delta(working_set.render())?;
+
+        Ok(())
+    }
+
+    fn _nur_record(&self) -> Record {
         let mut nur_record = Record::new();
         nur_record.push(
             NUR_VAR_RUN_PATH,
@@ -129,12 +246,20 @@ impl NurEngine {
             ),
         );
         if self.state.has_task_call {
-            // TODO: Should we remove this? Will always only include the main task, no sub tasks
+            // Only includes the main task, see task-path for the full path including sub tasks
             nur_record.push(
                 NUR_VAR_TASK_NAME,
                 Value::string(self.state.task_call[1].clone(), Span::unknown()), // strip "nur "
             );
         }
+        if let Some(task_name) = &self.state.task_name {
+            let task_path: Vec<&str> = task_name.split(' ').skip(1).collect(); // strip "nur"
+            nur_record.push(NUR_VAR_TASK_PATH, task_path.into_value(Span::unknown()));
+            nur_record.push(
+                NUR_VAR_TASK_ARGS,
+                self.state.task_args().to_vec().into_value(Span::unknown()),
+            );
+        }
         nur_record.push(
             NUR_VAR_CONFIG_DIR,
             Value::string(
@@ -149,18 +274,52 @@ impl NurEngine {
                 Span::unknown(),
             ),
         );
-        let mut working_set = StateWorkingSet::new(&self.engine_state);
-        let nur_var_id = working_set.add_variable(
-            NUR_NAME.as_bytes().into(),
-            Span::unknown(),
-            Type::Any,
-            false,
+        let nur_args = self.state.nur_args.as_ref();
+        let flag = |is_set: fn(&NurArgs) -> bool| {
+            Value::bool(nur_args.is_some_and(is_set), Span::unknown())
+        };
+        nur_record.push(
+            NUR_VAR_FLAGS,
+            Value::record(
+                record! {
+                    "quiet" => flag(|nur_args| nur_args.quiet_execution),
+                    "stdin" => flag(|nur_args| nur_args.attach_stdin),
+                    "ci" => flag(|nur_args| nur_args.ci_mode),
+                    "no-ci" => flag(|nur_args| nur_args.no_ci),
+                    "yes" => flag(|nur_args| nur_args.assume_yes),
+                    "here" => flag(|nur_args| nur_args.run_here),
+                    "use-nu-config" => flag(|nur_args| nur_args.use_nu_config),
+                    "log" => flag(|nur_args| nur_args.log_output),
+                    "enter-shell" => flag(|nur_args| nur_args.enter_shell),
+                },
+                Span::unknown(),
+            ),
+        );
+        nur_record.push(
+            NUR_VAR_IS_TTY,
+            Value::bool(io::stdout().is_terminal(), Span::unknown()),
+        );
+        nur_record.push(
+            NUR_VAR_NUR_VERSION,
+            Value::string(env!("CARGO_PKG_VERSION"), Span::unknown()),
+        );
+        nur_record.push(
+            NUR_VAR_NU_VERSION,
+            Value::string(NU_VERSION, Span::unknown()),
         );
-        self.stack
-            .add_var(nur_var_id, Value::record(nur_record, Span::unknown()));
-        self.engine_state.merge_delta(working_set.render())?;
 
-        Ok(())
+        nur_record
+    }
+
+    /// Update $nur after the task call or the parsed nur args changed
+    fn _update_nur_variable(&mut self) {
+        let working_set = StateWorkingSet::new(&self.engine_state);
+        if let Some(nur_var_id) = working_set.find_variable(NUR_NAME.as_bytes()) {
+            self.stack.add_var(
+                nur_var_id,
+                Value::record(self._nur_record(), Span::unknown()),
+            );
+        }
     }
 
     fn _finalise_nur_state(&mut self) {
@@ -176,14 +335,53 @@ impl NurEngine {
                 Value::string(task_name, Span::unknown()),
             );
         }
+
+        self._update_nur_variable();
     }
 
-    pub(crate) fn parse_args(&mut self) -> NurArgs {
-        parse_commandline_args(&self.state.args_to_nur.join(" "), &mut self.engine_state)
-            .unwrap_or_else(|_| std::process::exit(1))
+    pub(crate) fn parse_args(&mut self) -> NurResult<NurArgs> {
+        let nur_args =
+            parse_commandline_args(&self.state.args_to_nur.join(" "), &mut self.engine_state)?;
+
+        // Make the parsed flags available as $nur.flags and to nested "nur <task>" calls
+        self.state.nur_args = Some(nur_args.clone());
+        self._update_nur_variable();
+        self._register_nur_command()?;
+
+        Ok(nur_args)
     }
 
-    pub(crate) fn load_env(&mut self) -> NurResult<()> {
+    /// Load the encrypted secrets file and the secrets file (in this order, so local secrets
+    /// may override encrypted ones)
+    pub fn load_secrets(&mut self) -> NurResult<()> {
+        if self.state.encrypted_secrets_path.exists() {
+            let secrets = decrypt_secrets_file(
+                &self.state.encrypted_secrets_path,
+                env::var(NUR_ENV_NUR_SECRETS_IDENTITY).ok(),
+            )?;
+            self._load_secrets(secrets)?;
+        }
+        if self.state.secrets_path.exists() {
+            check_secrets_file_permissions(&self.state.secrets_path)?;
+            let secrets = fs::read_to_string(&self.state.secrets_path)?;
+            self._load_secrets(secrets)?;
+        }
+
+        Ok(())
+    }
+
+    fn _load_secrets(&mut self, secrets: String) -> NurResult<()> {
+        self.eval_and_merge_env(
+            load_secrets_script(),
+            PipelineData::Value(Value::string(secrets, Span::unknown()), None),
+        )?;
+
+        Ok(())
+    }
+
+    pub fn load_env(&mut self) -> NurResult<()> {
+        self._load_nu_config_file(self.state.nu_env_path.clone())?;
+
         if self.state.env_path.exists() {
             self.source_and_merge_env(self.state.env_path.clone(), PipelineData::empty())?;
         } else {
@@ -193,17 +391,129 @@ impl NurEngine {
         Ok(())
     }
 
-    pub(crate) fn load_config(&mut self) -> NurResult<()> {
+    pub fn load_config(&mut self) -> NurResult<()> {
+        self._load_config(true)
+    }
+
+    /// Load the config like [`NurEngine::load_config`], but only use already registered plugins
+    #[cfg(feature = "lsp")]
+    pub(crate) fn load_config_without_plugin_registration(&mut self) -> NurResult<()> {
+        self._load_config(false)
+    }
+
+    fn _load_config(&mut self, register_plugins: bool) -> NurResult<()> {
+        // Plugins are loaded before config.nu, so config and nurfiles may use them
+        self._load_plugins(register_plugins)?;
+
+        self._load_nu_config_file(self.state.nu_config_path.clone())?;
+
         if self.state.config_path.exists() {
             self.source_and_merge_env(self.state.config_path.clone(), PipelineData::empty())?;
         } else {
             self.eval_and_merge_env(get_default_nur_config(), PipelineData::empty())?;
         }
+        self._apply_ci_config();
+
+        Ok(())
+    }
+
+    /// Source the users nu env.nu or config.nu, only when requested by --use-nu-config
+    fn _load_nu_config_file(&mut self, nu_config_file: Option<PathBuf>) -> NurResult<()> {
+        let use_nu_config = self
+            .state
+            .nur_args
+            .as_ref()
+            .is_some_and(|nur_args| nur_args.use_nu_config);
+        if let Some(nu_config_file) = nu_config_file.filter(|path| use_nu_config && path.exists()) {
+            self.source_and_merge_env(nu_config_file, PipelineData::empty())?;
+        }
+
+        Ok(())
+    }
+
+    /// Use the project plugin registry, registering all plugins listed in .nur/plugins first
+    /// (unless `register_plugins` is false)
+    #[cfg(feature = "plugin")]
+    fn _load_plugins(&mut self, register_plugins: bool) -> NurResult<()> {
+        let registry_path = self.state.plugin_registry_path.clone();
+        self.engine_state.plugin_path = Some(registry_path.clone());
+
+        let plugins = if register_plugins {
+            read_plugin_list(&self.state)?
+        } else {
+            vec![]
+        };
+        let plugins_to_register = plugins_to_register(&self.state, &plugins);
+        if !plugins_to_register.is_empty() {
+            if let Some(registry_dir) = registry_path.parent() {
+                fs::create_dir_all(registry_dir)?;
+            }
+            for plugin in plugins_to_register {
+                self.eval(
+                    format!(
+                        "plugin add {}",
+                        escape_for_script_arg(&plugin.to_string_lossy())
+                    ),
+                    PipelineData::empty(),
+                )?;
+            }
+        }
+
+        if registry_path.exists() {
+            read_plugin_file(
+                &mut self.engine_state,
+                Some(nu_protocol::Spanned {
+                    item: registry_path.to_string_lossy().to_string(),
+                    span: Span::unknown(),
+                }),
+            );
+        }
 
         Ok(())
     }
 
-    pub(crate) fn load_nurfiles(&mut self) -> NurResult<()> {
+    #[cfg(not(feature = "plugin"))]
+    fn _load_plugins(&mut self, register_plugins: bool) -> NurResult<()> {
+        if register_plugins && self.state.plugins_path.exists() {
+            return Err(NurError::PluginsNotSupported(
+                self.state.plugins_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Format output for the CI system, this will disable colours
+    pub(crate) fn set_ci_provider(&mut self, ci_provider: CiProvider) -> NurResult<()> {
+        self.state.ci_provider = Some(ci_provider);
+        self._apply_ci_config();
+
+        // Nested task calls also need to create groups
+        self._register_nur_command()
+    }
+
+    fn _apply_ci_config(&mut self) {
+        if self.state.ci_provider.is_none() {
+            return;
+        }
+
+        let mut config = self.engine_state.get_config().as_ref().clone();
+        config.use_ansi_coloring = false;
+        self.engine_state.set_config(config);
+    }
+
+    fn _report_shell_error(&self, err: &ShellError) {
+        report_shell_error(&self.engine_state, err);
+        if let Some(annotation) = self
+            .state
+            .ci_provider
+            .and_then(|ci| ci.error_annotation(self.engine_state.files(), err))
+        {
+            println!("{}", annotation);
+        }
+    }
+
+    pub fn load_nurfiles(&mut self) -> NurResult<()> {
         if self.state.nurfile_path.exists() {
             self.source(self.state.nurfile_path.clone(), PipelineData::empty())?;
         }
@@ -254,6 +564,123 @@ impl NurEngine {
         self.get_def(task_name)
     }
 
+    /// Ensure all env vars declared by the task are set, missing ones will use their default
+    pub(crate) fn prepare_task_env(&mut self) -> NurResult<()> {
+        let Some(extra_description) = self
+            .get_task_def()
+            .map(|command| command.extra_description().to_string())
+        else {
+            return Ok(());
+        };
+        let task_name = self.get_short_task_name();
+
+        apply_task_env(
+            &mut self.engine_state,
+            &self.stack,
+            &task_name,
+            &extra_description,
+        )
+    }
+
+    /// Change to the directory the task should run in, either the invocation directory (when
+    /// run_here is set) or the directory declared by the task (relative to the project path)
+    pub(crate) fn set_task_dir(&mut self, run_here: bool) -> NurResult<()> {
+        let task_dir = if run_here {
+            self.state.run_path.clone()
+        } else {
+            let Some(extra_description) = self
+                .state
+                .has_task_call
+                .then(|| self.get_task_def())
+                .flatten()
+                .map(|command| command.extra_description().to_string())
+            else {
+                return Ok(());
+            };
+            let Some(task_dir) = resolve_task_dir(
+                &self.state.project_path,
+                &self.get_short_task_name(),
+                &extra_description,
+            )?
+            else {
+                return Ok(());
+            };
+            task_dir
+        };
+
+        self.stack.add_env_var(
+            String::from("PWD"),
+            Value::string(task_dir.to_string_lossy(), Span::unknown()),
+        );
+
+        Ok(())
+    }
+
+    /// Directory the task runs in, as set up by set_task_dir
+    fn _task_working_dir(&self) -> PathBuf {
+        self.engine_state
+            .cwd(Some(&self.stack))
+            .map(|cwd| cwd.into_std_path_buf())
+            .unwrap_or_else(|_| self.state.project_path.clone())
+    }
+
+    /// Directory the given task (without the "nur" prefix) would run in, used before the task
+    /// call is set up (like for completions)
+    pub(crate) fn task_working_dir_of(&self, task_name: &str, run_here: bool) -> PathBuf {
+        if run_here {
+            return self.state.run_path.clone();
+        }
+
+        self.get_def(format!("{} {}", NUR_NAME, task_name))
+            .and_then(|command| {
+                resolve_task_dir(
+                    &self.state.project_path,
+                    task_name,
+                    command.extra_description(),
+                )
+                .ok()
+                .flatten()
+            })
+            .unwrap_or_else(|| self._task_working_dir())
+    }
+
+    /// Ask for all missing required params of the task and add them to the task call, paths are
+    /// completed relative to the directory the task runs in (so call set_task_dir first)
+    pub(crate) fn prompt_missing_task_args(&mut self) -> NurResult<()> {
+        let Some(command) = self.get_task_def() else {
+            return Ok(());
+        };
+        let signature = command.signature();
+        let task_dir = self._task_working_dir();
+
+        let values = prompt_missing_positionals(&signature, self.state.task_args(), &task_dir)?;
+        if !values.is_empty() {
+            let mut raw_task_call = self.state.raw_task_call.clone();
+            raw_task_call.extend(values);
+            self.state.set_task_call(raw_task_call);
+            self._finalise_nur_state();
+        }
+
+        Ok(())
+    }
+
+    /// Ask for confirmation if the task requires it, fails if not confirmed
+    pub(crate) fn confirm_task(&mut self, assume_yes: bool) -> NurResult<()> {
+        let Some(extra_description) = self
+            .get_task_def()
+            .map(|command| command.extra_description().to_string())
+        else {
+            return Ok(());
+        };
+
+        confirm_task_run(
+            &self.get_short_task_name(),
+            &self.state.project_path,
+            &extra_description,
+            assume_yes,
+        )
+    }
+
     // Return task name without the "nur " prefix
     pub(crate) fn get_short_task_name(&self) -> String {
         let task_name = self.state.task_name.clone().unwrap();
@@ -266,8 +693,8 @@ impl NurEngine {
         file_path: Option<&str>,
         contents: String,
     ) -> NurResult<Arc<Block>> {
-        if file_path.is_some() {
-            self.engine_state.file = Some(PathBuf::from(file_path.unwrap()));
+        if let Some(file_path) = file_path {
+            self.engine_state.file = Some(PathBuf::from(file_path));
         }
 
         let mut working_set = StateWorkingSet::new(&self.engine_state);
@@ -282,22 +709,39 @@ impl NurEngine {
             if let Some(err) = working_set.parse_errors.first() {
                 report_parse_error(&working_set, err);
             }
+            if let Some(ci) = self.state.ci_provider {
+                for err in &working_set.parse_errors {
+                    if let Some(annotation) = ci.error_annotation(working_set.files(), err) {
+                        println!("{}", annotation);
+                    }
+                }
+            }
 
             Err(NurError::ParseErrors(working_set.parse_errors))
         }
     }
 
-    fn _execute_block(&mut self, block: &Block, input: PipelineData) -> NurResult<PipelineData> {
+    fn _execute_block(
+        &mut self,
+        block: &Block,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
         nu_engine::get_eval_block(&self.engine_state)(
             &self.engine_state,
             &mut self.stack,
             block,
             input,
         )
-        .map_err(|err| {
-            report_shell_error(&self.engine_state, &err);
-            std::process::exit(1);
-        })
+    }
+
+    fn _exit_code_from_shell_error(err: &ShellError) -> i32 {
+        match err {
+            ShellError::NonZeroExitCode {
+                exit_code,
+                span: _span,
+            } => (*exit_code).into(),
+            _ => 1,
+        }
     }
 
     fn _eval<S: ToString>(
@@ -316,18 +760,30 @@ impl NurEngine {
 
         let block = self._parse_nu_script(file_path, str_contents)?;
 
-        let result = self._execute_block(&block, input)?;
+        // Errors while loading files will abort, errors in printed task executions will be
+        // returned as exit code (same as errors while printing the result)
+        let result = match self._execute_block(&block, input) {
+            Ok(result) => result,
+            Err(err) => {
+                self._report_shell_error(&err);
+                if !print {
+                    return Err(NurError::ShellError(err));
+                }
+
+                return Ok(Self::_exit_code_from_shell_error(&err));
+            }
+        };
 
         // Merge env is requested
         if merge_env {
             match self.engine_state.cwd(Some(&self.stack)) {
                 Ok(_cwd) => {
                     if let Err(e) = self.engine_state.merge_env(&mut self.stack) {
-                        report_shell_error(&self.engine_state, &e);
+                        self._report_shell_error(&e);
                     }
                 }
                 Err(e) => {
-                    report_shell_error(&self.engine_state, &e);
+                    self._report_shell_error(&e);
                 }
             }
         }
@@ -342,26 +798,18 @@ impl NurEngine {
         match exit_details {
             Ok(()) => Ok(0),
             Err(err) => {
-                report_shell_error(&self.engine_state, &err);
-
-                match err {
-                    ShellError::NonZeroExitCode {
-                        exit_code,
-                        span: _span,
-                    } => Ok(exit_code.into()),
-                    _ => Ok(1),
-                }
+                self._report_shell_error(&err);
+
+                Ok(Self::_exit_code_from_shell_error(&err))
             }
         }
     }
 
-    // This is used in tests only currently
-    #[allow(dead_code)]
     pub fn eval<S: ToString>(&mut self, contents: S, input: PipelineData) -> NurResult<i32> {
         self._eval(None, contents, input, false, false)
     }
 
-    pub(crate) fn eval_and_print<S: ToString>(
+    pub fn eval_and_print<S: ToString>(
         &mut self,
         contents: S,
         input: PipelineData,
@@ -369,7 +817,7 @@ impl NurEngine {
         self._eval(None, contents, input, true, false)
     }
 
-    pub(crate) fn eval_and_merge_env<S: ToString>(
+    pub fn eval_and_merge_env<S: ToString>(
         &mut self,
         contents: S,
         input: PipelineData,
@@ -377,17 +825,13 @@ impl NurEngine {
         self._eval(None, contents, input, false, true)
     }
 
-    pub(crate) fn source<P: AsRef<Path>>(
-        &mut self,
-        file_path: P,
-        input: PipelineData,
-    ) -> NurResult<i32> {
+    pub fn source<P: AsRef<Path>>(&mut self, file_path: P, input: PipelineData) -> NurResult<i32> {
         let contents = fs::read_to_string(&file_path)?;
 
         self._eval(file_path.as_ref().to_str(), contents, input, false, false)
     }
 
-    pub(crate) fn source_and_merge_env<P: AsRef<Path>>(
+    pub fn source_and_merge_env<P: AsRef<Path>>(
         &mut self,
         file_path: P,
         input: PipelineData,
@@ -397,13 +841,105 @@ impl NurEngine {
         self._eval(file_path.as_ref().to_str(), contents, input, false, true)
     }
 
-    pub(crate) fn has_def<S: AsRef<str>>(&self, name: S) -> bool {
+    /// Run a task and capture its output instead of printing it
+    ///
+    /// The task call is given without the "nur" prefix, like `["some-task", "sub-task", "arg"]`.
+    /// Shell errors are returned instead of ending the process.
+    pub fn run_task<I, S>(&mut self, task_call: I) -> NurResult<NurTaskOutput>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut call = vec![String::from(NUR_NAME)];
+        call.extend(
+            task_call
+                .into_iter()
+                .map(|arg| escape_for_script_arg(arg.as_ref())),
+        );
+
+        self.eval_and_capture(call.join(" "))
+    }
+
+    /// Evaluate the given nu code and capture its output instead of printing it
+    ///
+    /// Captures stdout and stderr of the whole process while running, only one capture runs at
+    /// a time.
+    pub fn eval_and_capture<S: ToString>(&mut self, contents: S) -> NurResult<NurTaskOutput> {
+        let capture = OutputCapture::start()?;
+        let result = self.eval_to_value(contents);
+        let (stdout, stderr) = capture.finish();
+
+        result.map(|output| NurTaskOutput {
+            stdout,
+            stderr,
+            ..output
+        })
+    }
+
+    /// Evaluate the given nu code and return its value, printed output is not captured
+    pub(crate) fn eval_to_value<S: ToString>(&mut self, contents: S) -> NurResult<NurTaskOutput> {
+        let block = self._parse_nu_script(None, contents.to_string())?;
+        let mut stack = self.stack.clone().collect_value();
+        let result = nu_engine::get_eval_block(&self.engine_state)(
+            &self.engine_state,
+            &mut stack,
+            &block,
+            PipelineData::empty(),
+        )
+        .and_then(|data| data.into_value(Span::unknown()));
+
+        match result {
+            Ok(value) => Ok(NurTaskOutput {
+                exit_code: 0,
+                value,
+                stdout: String::new(),
+                stderr: String::new(),
+            }),
+            Err(err @ ShellError::NonZeroExitCode { .. }) => Ok(NurTaskOutput {
+                exit_code: Self::_exit_code_from_shell_error(&err),
+                value: Value::nothing(Span::unknown()),
+                stdout: String::new(),
+                stderr: String::new(),
+            }),
+            Err(err) => Err(NurError::ShellError(err)),
+        }
+    }
+
+    /// List all available tasks (without the "nur " prefix), sorted by name
+    pub fn list_tasks(&self) -> Vec<String> {
+        self.list_task_decls()
+            .into_iter()
+            .map(|(task_name, _)| task_name)
+            .collect()
+    }
+
+    pub(crate) fn list_task_decls(&self) -> Vec<(String, DeclId)> {
+        let mut task_decls: Vec<(String, DeclId)> = self
+            .engine_state
+            .get_decls_sorted(false)
+            .into_iter()
+            .filter_map(|(name, decl_id)| {
+                let name = String::from_utf8_lossy(&name).to_string();
+                let decl = self.engine_state.get_decl(decl_id);
+                if name.starts_with("nur ") && decl.signature().category == Category::Default {
+                    Some((String::from(&name[4..]), decl_id))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        task_decls.sort_by(|a, b| a.0.cmp(&b.0));
+
+        task_decls
+    }
+
+    pub fn has_def<S: AsRef<str>>(&self, name: S) -> bool {
         self.engine_state
             .find_decl(name.as_ref().as_bytes(), &[])
             .is_some()
     }
 
-    pub(crate) fn get_def<S: AsRef<str>>(&self, name: S) -> Option<&dyn Command> {
+    pub fn get_def<S: AsRef<str>>(&self, name: S) -> Option<&dyn Command> {
         if let Some(decl_id) = self.engine_state.find_decl(name.as_ref().as_bytes(), &[]) {
             Some(self.engine_state.get_decl(decl_id))
         } else {
@@ -412,26 +948,42 @@ impl NurEngine {
     }
 
     pub(crate) fn print_help(&mut self, command: &dyn Command) {
-        let full_help = get_full_help(command, &self.engine_state, &mut self.stack);
+        let full_help = match TaskHelp::new(command) {
+            Some(task_help) => get_full_help(&task_help, &self.engine_state, &mut self.stack),
+            None => get_full_help(command, &self.engine_state, &mut self.stack),
+        };
 
         let _ = std::panic::catch_unwind(move || stdout_write_all_and_flush(full_help));
     }
 
+    /// Set up the shell for --enter-shell, adding the nur prompt and the reload command
+    fn _prepare_repl(&mut self) -> NurResult<()> {
+        // The shell does not run a file, otherwise sourcing the last loaded nurfile again
+        // (using reload) would be detected as a circular import
+        self.engine_state.file = None;
+        self.eval_and_merge_env(get_nur_shell_setup(), PipelineData::empty())?;
+
+        self.engine_state.is_interactive = true;
+
+        Ok(())
+    }
+
     pub(crate) fn run_repl(&mut self) -> NurResult<()> {
-        match evaluate_repl(
+        self._prepare_repl()?;
+
+        // Keep the shell history per project in .nur/shell/
+        fs::create_dir_all(&self.state.shell_config_dir)?;
+
+        run_shell(
             &mut self.engine_state,
             self.stack.clone(),
-            None,
-            None,
-            std::time::Instant::now(),
-        ) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(EnteredShellError()),
-        }
+            &self.state.shell_config_dir,
+        )
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::useless_vec)]
 mod tests {
     use super::*;
     use crate::names::{
@@ -459,14 +1011,33 @@ mod tests {
         assert!(_has_decl(&mut engine_state, "uniq"));
         assert!(_has_decl(&mut engine_state, "help"));
         assert!(_has_decl(&mut engine_state, "str"));
-        assert!(_has_decl(&mut engine_state, "format pattern"));
         assert!(_has_decl(&mut engine_state, "history"));
-        assert!(_has_decl(&mut engine_state, "explore"));
+        assert_eq!(
+            _has_decl(&mut engine_state, "explore"),
+            cfg!(feature = "explore")
+        );
+        assert_eq!(
+            _has_decl(&mut engine_state, "format pattern"),
+            cfg!(feature = "extra-commands")
+        );
         assert!(_has_decl(&mut engine_state, "print"));
         assert!(_has_decl(&mut engine_state, "nu-highlight"));
         assert!(_has_decl(&mut engine_state, "nur"));
     }
 
+    #[test]
+    fn test_compiled_command_sets() {
+        let command_sets = compiled_command_sets();
+
+        assert!(command_sets.starts_with(&["lang", "shell"]));
+        assert_eq!(command_sets.last(), Some(&"nur"));
+        assert_eq!(
+            command_sets.contains(&"extra"),
+            cfg!(feature = "extra-commands")
+        );
+        assert_eq!(command_sets.contains(&"explore"), cfg!(feature = "explore"));
+    }
+
     #[test]
     fn test_init_engine_state_will_set_nu_version() {
         let temp_dir = tempdir().unwrap();
@@ -604,6 +1175,71 @@ mod tests {
         _cleanup_nur_engine(&temp_dir);
     }
 
+    #[test]
+    fn test_nur_engine_will_load_nu_config_if_requested() {
+        let temp_dir = tempdir().unwrap();
+        let nu_config_dir = tempdir().unwrap();
+
+        let nu_env_path = nu_config_dir.path().join("env.nu");
+        fs::write(&nu_env_path, "def nu-env-command [] {}").unwrap();
+        let nu_config_path = nu_config_dir.path().join("config.nu");
+        fs::write(
+            &nu_config_path,
+            "def nu-config-command [] {}\n$env.LOADED_CONFIG = \"nu\"",
+        )
+        .unwrap();
+        let config_dir = temp_dir.path().join(NUR_CONFIG_DIR);
+        fs::create_dir(config_dir.clone()).unwrap();
+        fs::write(
+            config_dir.join(NUR_CONFIG_CONFIG_FILENAME),
+            "$env.LOADED_CONFIG = $\"($env.LOADED_CONFIG? | default none), nur\"",
+        )
+        .unwrap();
+
+        for use_nu_config in [false, true] {
+            let mut nur_engine = _prepare_nur_engine(&temp_dir);
+            nur_engine.state.nu_env_path = Some(nu_env_path.clone());
+            nur_engine.state.nu_config_path = Some(nu_config_path.clone());
+            if use_nu_config {
+                nur_engine
+                    .state
+                    .args_to_nur
+                    .push(String::from("--use-nu-config"));
+            }
+            nur_engine.parse_args().unwrap();
+
+            nur_engine.load_env().unwrap();
+            nur_engine.load_config().unwrap();
+
+            assert_eq!(
+                _has_decl(&mut nur_engine.engine_state, "nu-env-command"),
+                use_nu_config
+            );
+            assert_eq!(
+                _has_decl(&mut nur_engine.engine_state, "nu-config-command"),
+                use_nu_config
+            );
+            // nu config is loaded first, so .nur/config.nu may change its settings
+            let loaded_config = nur_engine
+                .engine_state
+                .get_env_var("LOADED_CONFIG")
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string();
+            assert_eq!(
+                loaded_config,
+                if use_nu_config {
+                    "nu, nur"
+                } else {
+                    "none, nur"
+                }
+            );
+        }
+
+        _cleanup_nur_engine(&temp_dir);
+    }
+
     #[test]
     fn test_nur_engine_will_allow_scripts() {
         let temp_dir = tempdir().unwrap();
@@ -685,6 +1321,66 @@ mod tests {
         assert!(nur_engine.get_short_task_name() == "some-task sub-task");
     }
 
+    #[test]
+    fn test_nur_engine_will_update_nur_variable() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile.write_all(b"def \"nur some-task\" [] {}").unwrap();
+
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        let output = nur_engine
+            .eval_and_capture("[($nur.task-path | str join ' ') ($nur.task-args | str join ' ') $nur.flags.quiet $nur.nu-version] | to nuon")
+            .unwrap();
+        assert_eq!(
+            output.to_string_lossy(),
+            "[some-task, sub-task, false, \"0.101.0\"]"
+        );
+    }
+
+    #[test]
+    fn test_nur_engine_will_use_raw_task_args() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(NUR_FILE),
+            "def \"nur some-task\" [arg: string, --flag: string] {}",
+        )
+        .unwrap();
+
+        let args = vec![
+            String::from("nur"),
+            String::from("some-task"),
+            String::from("a b"),
+            String::from("--flag=c \"d\""),
+        ];
+        let nur_state = NurState::new(temp_dir.path().to_path_buf(), args).unwrap();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        let output = nur_engine
+            .eval_and_capture("$nur.task-args | to nuon")
+            .unwrap();
+        assert_eq!(
+            output.to_string_lossy(),
+            "[\"a b\", \"--flag=c \\\"d\\\"\"]"
+        );
+        assert_eq!(
+            nur_engine.state.task_call[2..],
+            [
+                escape_for_script_arg("a b"),
+                escape_for_script_arg("--flag=c \"d\"")
+            ]
+        );
+    }
+
     #[test]
     fn test_nur_engine_will_set_env() {
         let temp_dir = tempdir().unwrap();
@@ -712,4 +1408,143 @@ mod tests {
             .get_env_var(NUR_ENV_NUR_TASK_CALL)
             .is_some());
     }
+
+    #[test]
+    fn test_nur_engine_will_prepare_task_env() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile
+            .write_all(b"# Some task\n#\n# env: NUR_TEST_MISSING_A\n# env: NUR_TEST_MISSING_B Some description\n# env: NUR_TEST_WITH_DEFAULT=value\ndef \"nur some-task\" [] {}")
+            .unwrap();
+
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        match nur_engine.prepare_task_env() {
+            Err(NurError::MissingTaskEnv(task_name, missing)) => {
+                assert_eq!(task_name, "some-task");
+                assert_eq!(missing, "NUR_TEST_MISSING_A, NUR_TEST_MISSING_B");
+            }
+            _ => panic!("missing env vars should be reported"),
+        }
+        assert!(nur_engine
+            .engine_state
+            .get_env_var("NUR_TEST_WITH_DEFAULT")
+            .is_some_and(|value| value.as_str().unwrap() == "value"));
+    }
+
+    #[test]
+    fn test_nur_engine_will_set_task_dir() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+        std::fs::create_dir(temp_dir.path().join("frontend")).unwrap();
+
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile
+            .write_all(b"# Some task\n#\n# dir: frontend\ndef \"nur some-task\" [] {}")
+            .unwrap();
+
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        nur_engine.set_task_dir(false).unwrap();
+        assert_eq!(
+            nur_engine
+                .engine_state
+                .cwd(Some(&nur_engine.stack))
+                .unwrap()
+                .as_std_path(),
+            temp_dir.path().join("frontend")
+        );
+        // Also used to complete paths when prompting for task args
+        assert_eq!(
+            nur_engine._task_working_dir(),
+            temp_dir.path().join("frontend")
+        );
+
+        nur_engine.set_task_dir(true).unwrap();
+        assert_eq!(nur_engine._task_working_dir(), nur_engine.state.run_path);
+    }
+
+    #[test]
+    fn test_task_help_hides_task_metadata() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile
+            .write_all(
+                b"# Some task\n#\n# More text\n# env: API_TOKEN The token\n# dir: frontend\n\
+                # confirm: Really?\ndef \"nur some-task\" [] {}",
+            )
+            .unwrap();
+
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        let task_help = TaskHelp::new(nur_engine.get_def("nur some-task").unwrap()).unwrap();
+        let extra_description = task_help.extra_description();
+        assert!(extra_description.starts_with("More text\n\n"));
+        assert!(extra_description.contains("API_TOKEN"));
+        assert!(extra_description.contains("frontend"));
+        assert!(extra_description.contains("Really?"));
+        for marker in ["env:", "dir:", "confirm"] {
+            assert!(!extra_description.contains(marker));
+        }
+    }
+
+    #[test]
+    fn test_nur_engine_will_fail_for_missing_task_dir() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        let mut nurfile = File::create(&nurfile_path).unwrap();
+        nurfile
+            .write_all(b"# Some task\n#\n# dir: missing\ndef \"nur some-task\" [] {}")
+            .unwrap();
+
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        assert!(matches!(
+            nur_engine.set_task_dir(false),
+            Err(NurError::TaskDirNotFound(_, _))
+        ));
+        // Running in the current directory ignores the declared directory
+        assert!(nur_engine.set_task_dir(true).is_ok());
+    }
+
+    #[test]
+    fn test_nur_engine_will_prepare_repl() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        nur_engine._prepare_repl().unwrap();
+
+        assert!(nur_engine.engine_state.file.is_none());
+        assert!(nur_engine.engine_state.is_interactive);
+        assert!(_has_decl(&mut nur_engine.engine_state, "reload"));
+        assert!(nur_engine
+            .engine_state
+            .get_env_var("PROMPT_COMMAND")
+            .is_some());
+        assert_eq!(
+            nur_engine.engine_state.get_config().hooks.pre_prompt.len(),
+            1
+        );
+        assert!(!nur_engine.engine_state.get_config().show_banner);
+    }
 }
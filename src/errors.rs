@@ -2,7 +2,7 @@ use miette::Diagnostic;
 use nu_protocol::{ParseError, ShellError};
 use thiserror::Error;
 
-pub(crate) type NurResult<T> = Result<T, NurError>;
+pub type NurResult<T> = Result<T, NurError>;
 
 #[derive(Clone, Debug, Error, Diagnostic)]
 pub enum NurError {
@@ -41,6 +41,80 @@ pub enum NurError {
     #[error("You cannot use {0} and {1} together")]
     #[diagnostic()]
     InvalidNurCall(String, String),
+
+    #[error("Unsupported shell '{0}' for completions (use bash, zsh, fish, nu or powershell)")]
+    #[diagnostic()]
+    UnsupportedShell(String),
+
+    #[error(
+        "Unsupported task runner '{0}' (use just, make, npm, task, b5, lets, tusk or toolkit)"
+    )]
+    #[diagnostic()]
+    UnsupportedTaskRunner(String),
+
+    #[error("Could not generate nurfile: {0}")]
+    #[diagnostic()]
+    NurifyError(String),
+
+    #[error("File {0} already exists, use --force to overwrite")]
+    #[diagnostic()]
+    FileExists(String),
+
+    #[error("Language server error: {0}")]
+    #[diagnostic()]
+    LanguageServerError(String),
+
+    #[error("Could not format {0}: {1}")]
+    #[diagnostic()]
+    FormatError(String, String),
+
+    #[error("No previous task run found in history")]
+    #[diagnostic()]
+    NoTaskHistory(),
+
+    #[error("Last run of task '{0}' had secrets in its arguments and cannot be replayed")]
+    #[diagnostic()]
+    MaskedTaskHistory(String),
+
+    #[error("Secrets file '{0}' may be read by other users, restrict it using `chmod 600 {0}`")]
+    #[diagnostic()]
+    InsecureSecretsFile(String),
+
+    #[error("Secrets file '{0}' is encrypted, set NUR_SECRETS_IDENTITY to your age identity file")]
+    #[diagnostic()]
+    MissingSecretsIdentity(String),
+
+    #[error("Could not decrypt secrets file '{0}': {1}")]
+    #[diagnostic()]
+    SecretsDecryptionError(String, String),
+
+    #[error("Task '{0}' requires environment variables which are not set: {1}")]
+    #[diagnostic()]
+    MissingTaskEnv(String, String),
+
+    #[error("Directory '{1}' of task '{0}' does not exist")]
+    #[diagnostic()]
+    TaskDirNotFound(String, String),
+
+    #[error("Task '{0}' requires confirmation, use --yes to run it without a terminal")]
+    #[diagnostic()]
+    ConfirmationRequired(String),
+
+    #[error("Task '{0}' was not confirmed")]
+    #[diagnostic()]
+    TaskNotConfirmed(String),
+
+    #[error("Invalid report '{0}' (use junit=<path>)")]
+    #[diagnostic()]
+    InvalidReport(String),
+
+    #[error("Invalid events output '{0}' (use jsonl or jsonl=<path>)")]
+    #[diagnostic()]
+    InvalidEvents(String),
+
+    #[error("Plugins are listed in {0}, but nur was built without plugin support")]
+    #[diagnostic()]
+    PluginsNotSupported(String),
 }
 
 impl From<std::io::Error> for NurError {
@@ -0,0 +1,388 @@
+use crate::errors::{NurError, NurResult};
+use crate::report::{TaskResult, TaskResults};
+use crate::secrets::mask_secrets;
+use chrono::Local;
+use nu_protocol::engine::EngineState;
+use nu_protocol::ir::{Instruction, IrBlock};
+use nu_protocol::{Category, DeclId, PipelineData, ShellError};
+use serde_json::{json, Value as JsonValue};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+const EVENTS_FORMAT_JSONL: &str = "jsonl";
+
+struct EventSink {
+    writer: Box<dyn Write + Send>,
+    secrets: Vec<String>,
+}
+
+/// Writes machine readable events of the nur execution as JSON lines
+#[derive(Clone)]
+pub(crate) struct EventWriter {
+    sink: Arc<Mutex<EventSink>>,
+}
+
+impl fmt::Debug for EventWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventWriter").finish_non_exhaustive()
+    }
+}
+
+/// Writer for the original stderr, so events are not masked or captured with the task output
+fn stderr_writer() -> NurResult<Box<dyn Write + Send>> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsFd;
+
+        Ok(Box::new(File::from(
+            io::stderr().as_fd().try_clone_to_owned()?,
+        )))
+    }
+
+    #[cfg(not(unix))]
+    Ok(Box::new(io::stderr()))
+}
+
+impl EventWriter {
+    /// Create the writer from the `--events` argument, either "jsonl" (writing to stderr) or
+    /// "jsonl=<path>", relative paths are based on the given path
+    pub(crate) fn from_argument<P: AsRef<Path>>(
+        argument: &str,
+        base_path: P,
+    ) -> NurResult<EventWriter> {
+        let (format, path) = match argument.split_once('=') {
+            Some((format, path)) => (format, Some(path)),
+            None => (argument, None),
+        };
+        if format != EVENTS_FORMAT_JSONL || path.is_some_and(|path| path.is_empty()) {
+            return Err(NurError::InvalidEvents(String::from(argument)));
+        }
+
+        let writer: Box<dyn Write + Send> = match path {
+            Some(path) => {
+                let path = base_path.as_ref().join(path);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                Box::new(File::create(path)?)
+            }
+            None => stderr_writer()?,
+        };
+
+        Ok(EventWriter::new(writer))
+    }
+
+    pub(crate) fn new(writer: Box<dyn Write + Send>) -> EventWriter {
+        EventWriter {
+            sink: Arc::new(Mutex::new(EventSink {
+                writer,
+                secrets: vec![],
+            })),
+        }
+    }
+
+    /// Set the secrets to mask in all following events
+    pub(crate) fn set_secrets(&self, secrets: &[String]) {
+        let mut sink = self.sink.lock().unwrap_or_else(|err| err.into_inner());
+        sink.secrets = secrets.to_vec();
+    }
+
+    /// Write the event, the data needs to be a JSON object
+    pub(crate) fn emit(&self, event: &str, data: JsonValue) {
+        let mut entry = json!({
+            "event": event,
+            "timestamp": Local::now().to_rfc3339(),
+        });
+        if let (Some(entry), JsonValue::Object(data)) = (entry.as_object_mut(), data) {
+            entry.extend(data);
+        }
+
+        let mut sink = self.sink.lock().unwrap_or_else(|err| err.into_inner());
+        let line = mask_secrets(&entry.to_string(), &sink.secrets);
+        let _ = writeln!(sink.writer, "{}", line);
+        let _ = sink.writer.flush();
+    }
+}
+
+/// Emit events for sub task calls and spawned external commands and collect the results of sub
+/// tasks while evaluating code
+pub(crate) fn track_calls(
+    engine_state: &EngineState,
+    events: Option<EventWriter>,
+    results: Option<TaskResults>,
+) -> NurResult<()> {
+    let debugger = CallDebugger {
+        events,
+        results,
+        run_external_id: engine_state.find_decl(b"run-external", &[]),
+        task_calls: vec![],
+    };
+    engine_state
+        .activate_debugger(Box::new(debugger))
+        .map_err(|err| NurError::InitError(err.to_string()))?;
+
+    Ok(())
+}
+
+/// Debugger watching all call instructions, this is the only hook nu provides into evaluation
+#[derive(Debug)]
+struct CallDebugger {
+    events: Option<EventWriter>,
+    results: Option<TaskResults>,
+    run_external_id: Option<DeclId>,
+    task_calls: Vec<(String, Instant)>,
+}
+
+enum CallKind {
+    Task(String),
+    External,
+}
+
+impl CallDebugger {
+    fn call_kind(
+        &self,
+        engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+    ) -> Option<CallKind> {
+        let Some(Instruction::Call { decl_id, .. }) = ir_block.instructions.get(instruction_index)
+        else {
+            return None;
+        };
+        if Some(*decl_id) == self.run_external_id {
+            return Some(CallKind::External);
+        }
+
+        let decl = engine_state.get_decl(*decl_id);
+        let task_name = decl.name().strip_prefix("nur ")?;
+        if decl.signature().category != Category::Default {
+            return None;
+        }
+
+        Some(CallKind::Task(String::from(task_name)))
+    }
+}
+
+impl nu_protocol::debugger::Debugger for CallDebugger {
+    fn enter_instruction(
+        &mut self,
+        engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+        _registers: &[PipelineData],
+    ) {
+        match self.call_kind(engine_state, ir_block, instruction_index) {
+            Some(CallKind::Task(task_name)) => {
+                // The outermost task call is the executed task itself
+                if let (Some(events), Some((parent_name, _))) =
+                    (&self.events, self.task_calls.last())
+                {
+                    events.emit(
+                        "subtask_started",
+                        json!({
+                            "task": task_name,
+                            "parent": parent_name,
+                            "depth": self.task_calls.len(),
+                        }),
+                    );
+                }
+                self.task_calls.push((task_name, Instant::now()));
+            }
+            Some(CallKind::External) => {
+                let command = ir_block
+                    .spans
+                    .get(instruction_index)
+                    .map(|span| {
+                        String::from_utf8_lossy(engine_state.get_span_contents(*span))
+                            .trim_start_matches('^')
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                if let Some(events) = &self.events {
+                    events.emit(
+                        "external_spawned",
+                        json!({
+                            "command": command,
+                            "task": self.task_calls.last().map(|(task_name, _)| task_name),
+                        }),
+                    );
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn leave_instruction(
+        &mut self,
+        engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+        _registers: &[PipelineData],
+        error: Option<&ShellError>,
+    ) {
+        let Some(CallKind::Task(_)) = self.call_kind(engine_state, ir_block, instruction_index)
+        else {
+            return;
+        };
+        let Some((task_name, started_at)) = self.task_calls.pop() else {
+            return;
+        };
+
+        let Some((parent_name, _)) = self.task_calls.last() else {
+            return;
+        };
+        if let Some(events) = &self.events {
+            events.emit(
+                "subtask_finished",
+                json!({
+                    "task": task_name,
+                    "parent": parent_name,
+                    "depth": self.task_calls.len(),
+                    "failed": error.is_some(),
+                    "duration_ms": started_at.elapsed().as_millis() as u64,
+                }),
+            );
+        }
+        if let Some(results) = &self.results {
+            results.push(TaskResult {
+                name: task_name,
+                exit_code: error.and_then(ShellError::exit_code).unwrap_or(0),
+                duration: started_at.elapsed(),
+                stderr: error.map(ToString::to_string).unwrap_or_default(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::SharedBuffer;
+    use crate::engine::{init_engine_state, NurEngine};
+    use crate::names::NUR_FILE;
+    use crate::report::Report;
+    use crate::state::NurState;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    fn _events(buffer: &SharedBuffer) -> Vec<JsonValue> {
+        buffer
+            .to_string_lossy()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_event_writer_from_argument() {
+        let temp_dir = tempdir().unwrap();
+
+        assert!(EventWriter::from_argument("jsonl", temp_dir.path()).is_ok());
+        assert!(EventWriter::from_argument("jsonl=logs/events.jsonl", temp_dir.path()).is_ok());
+        assert!(temp_dir.path().join("logs/events.jsonl").exists());
+        assert!(EventWriter::from_argument("jsonl=", temp_dir.path()).is_err());
+        assert!(EventWriter::from_argument("xml", temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_emit_masks_secrets() {
+        let buffer = SharedBuffer::new();
+        let events = EventWriter::new(Box::new(buffer.clone()));
+        events.set_secrets(&[String::from("secret-value")]);
+        events.emit("task_started", json!({"args": ["--token=secret-value"]}));
+
+        let events = _events(&buffer);
+        assert_eq!(events[0]["event"], "task_started");
+        assert_eq!(events[0]["args"], json!(["--token=********"]));
+        assert!(events[0]["timestamp"].is_string());
+    }
+
+    #[test]
+    fn test_track_calls() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(NUR_FILE),
+            "def \"nur inner\" [] { ^echo hello }\ndef \"nur outer\" [] { nur inner }",
+        )
+        .unwrap();
+
+        let nur_state = NurState::new(
+            temp_dir.path().to_path_buf(),
+            vec![String::from("nur"), String::from("outer")],
+        )
+        .unwrap();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        let buffer = SharedBuffer::new();
+        let events = EventWriter::new(Box::new(buffer.clone()));
+        track_calls(&nur_engine.engine_state, Some(events), None).unwrap();
+        nur_engine.run_task(["outer"]).unwrap();
+        nur_engine.engine_state.deactivate_debugger().unwrap();
+
+        let events = _events(&buffer);
+        let names: Vec<&str> = events
+            .iter()
+            .map(|event| event["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["subtask_started", "external_spawned", "subtask_finished"]
+        );
+        assert_eq!(events[0]["task"], "inner");
+        assert_eq!(events[0]["parent"], "outer");
+        assert_eq!(events[1]["command"], "echo");
+        assert_eq!(events[1]["task"], "inner");
+        assert_eq!(events[2]["failed"], false);
+    }
+
+    #[test]
+    fn test_track_calls_collects_sub_task_results() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(NUR_FILE),
+            "def \"nur build\" [] { }\ndef \"nur lint\" [] { error make {msg: \"lint failed\"} }\ndef \"nur all\" [] { nur build; nur --quiet lint }",
+        )
+        .unwrap();
+
+        let nur_state = NurState::new(
+            temp_dir.path().to_path_buf(),
+            vec![String::from("nur"), String::from("all")],
+        )
+        .unwrap();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+        nur_engine.load_env().unwrap();
+        nur_engine.load_config().unwrap();
+        nur_engine.load_nurfiles().unwrap();
+
+        let results = TaskResults::new();
+        track_calls(&nur_engine.engine_state, None, Some(results.clone())).unwrap();
+        assert!(nur_engine.run_task(["all"]).is_err());
+        nur_engine.engine_state.deactivate_debugger().unwrap();
+
+        let results = results.take();
+        let names: Vec<&str> = results.iter().map(|result| result.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "lint"]);
+        assert_eq!(results[0].exit_code, 0);
+        assert_eq!(results[1].exit_code, 1);
+        assert!(results[1].stderr.contains("lint failed"));
+
+        // Every sub task is a testcase of the report
+        let report_path = temp_dir.path().join("report.xml");
+        Report::JUnit(report_path.clone())
+            .write("project", Duration::from_secs(1), &results)
+            .unwrap();
+        let xml = fs::read_to_string(report_path).unwrap();
+        assert_eq!(xml.matches("<testcase ").count(), 2);
+        assert!(xml.contains("<testcase name=\"build\""));
+        assert!(xml.contains("<testcase name=\"lint\""));
+    }
+}
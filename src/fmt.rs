@@ -0,0 +1,466 @@
+use crate::engine::NurEngine;
+use crate::errors::{NurError, NurResult};
+use nu_parser::{flatten_block, lex, FlatShape, TokenContents};
+use nu_protocol::engine::{EngineState, StateWorkingSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDENT: &str = "    ";
+
+/// Structure of some nu source, all positions are byte offsets
+#[derive(Debug, Default)]
+struct Layout {
+    /// Blocks, lists and sub expressions as (opener, closer, is_signature)
+    blocks: Vec<(usize, usize, bool)>,
+    /// Multi line items (like strings), those must be kept as they are
+    verbatim: Vec<(usize, usize)>,
+    /// Start of all comments
+    comments: Vec<usize>,
+}
+
+fn closing_delimiter(opener: u8) -> Option<u8> {
+    match opener {
+        b'{' => Some(b'}'),
+        b'[' => Some(b']'),
+        b'(' => Some(b')'),
+        _ => None,
+    }
+}
+
+fn is_def_keyword(item: &[u8]) -> bool {
+    matches!(item, b"def" | b"extern")
+}
+
+/// Check whether the next item of the statement will be the signature of a def
+fn is_signature_position(statement: &[&[u8]]) -> bool {
+    let items: Vec<&[u8]> = statement
+        .iter()
+        .copied()
+        .filter(|item| !item.starts_with(b"-"))
+        .collect();
+
+    match items.as_slice() {
+        [keyword, _] => is_def_keyword(keyword),
+        [export, keyword, _] => *export == b"export" && is_def_keyword(keyword),
+        _ => false,
+    }
+}
+
+/// Lex the given range, descending into all blocks, returns false if lexing failed
+fn collect_layout(source: &[u8], start: usize, end: usize, layout: &mut Layout) -> bool {
+    let (tokens, err) = lex(&source[start..end], start, &[], &[], false);
+    if err.is_some() {
+        return false;
+    }
+
+    let mut statement: Vec<&[u8]> = Vec::new();
+    for token in tokens {
+        let (item_start, item_end) = (token.span.start, token.span.end);
+        let contents = &source[item_start..item_end];
+        match token.contents {
+            TokenContents::Comment => layout.comments.push(item_start),
+            TokenContents::Item => {
+                let is_signature = is_signature_position(&statement);
+                statement.push(contents);
+
+                let is_block = contents.len() >= 2
+                    && closing_delimiter(contents[0]) == contents.last().copied();
+                if is_block {
+                    let saved_lengths = (
+                        layout.blocks.len(),
+                        layout.verbatim.len(),
+                        layout.comments.len(),
+                    );
+                    if collect_layout(source, item_start + 1, item_end - 1, layout) {
+                        layout.blocks.push((item_start, item_end - 1, is_signature));
+                        continue;
+                    }
+                    layout.blocks.truncate(saved_lengths.0);
+                    layout.verbatim.truncate(saved_lengths.1);
+                    layout.comments.truncate(saved_lengths.2);
+                }
+                if contents.contains(&b'\n') {
+                    layout.verbatim.push((item_start, item_end));
+                }
+            }
+            TokenContents::Eol | TokenContents::Semicolon => statement.clear(),
+            _ => {}
+        }
+    }
+
+    true
+}
+
+/// Ensure comments start with "# ", keeping things like "#!" or "##" as they are
+fn normalize_comment(comment: &str) -> String {
+    match comment.strip_prefix('#') {
+        Some(text) if !text.is_empty() && !text.starts_with([' ', '\t', '#', '!']) => {
+            format!("# {}", text)
+        }
+        _ => comment.to_string(),
+    }
+}
+
+fn is_def_line(content: &str) -> bool {
+    let mut words = content.split_whitespace();
+    match words.next() {
+        Some("export") => words
+            .next()
+            .is_some_and(|word| is_def_keyword(word.as_bytes())),
+        Some(word) => is_def_keyword(word.as_bytes()),
+        None => false,
+    }
+}
+
+#[derive(Debug)]
+enum Line {
+    Blank,
+    Verbatim(String),
+    Code {
+        depth: usize,
+        position: usize,
+        content: String,
+        is_comment: bool,
+    },
+}
+
+/// Format nu source, returns None if the source could not be tokenized
+///
+/// Only whitespace and comment spacing are changed: lines are indented by their nesting level,
+/// task doc comments are separated from the code before, parameter comments in signatures are
+/// aligned and multiple blank lines are collapsed.
+pub(crate) fn format_source(source: &str) -> Option<String> {
+    let bytes = source.as_bytes();
+    let mut layout = Layout::default();
+    if !collect_layout(bytes, 0, bytes.len(), &mut layout) {
+        return None;
+    }
+
+    let mut line_starts = vec![0];
+    line_starts.extend(
+        bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == b'\n')
+            .map(|(index, _)| index + 1),
+    );
+    let line_of = |offset: usize| line_starts.partition_point(|start| *start <= offset) - 1;
+    let is_verbatim = |offset: usize| {
+        layout
+            .verbatim
+            .iter()
+            .any(|(start, end)| *start < offset && offset < *end)
+    };
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (line_index, text) in source.split('\n').enumerate() {
+        let line_start = line_starts[line_index];
+        if is_verbatim(line_start) {
+            lines.push(Line::Verbatim(text.to_string()));
+            continue;
+        }
+
+        let content = text.trim_start();
+        let position = line_start + text.len() - content.len();
+        // Keep trailing whitespace if a multi line string starts in this line
+        let content = if is_verbatim(line_start + text.len()) {
+            content
+        } else {
+            content.trim_end()
+        };
+        if content.is_empty() {
+            lines.push(Line::Blank);
+            continue;
+        }
+
+        // Closing delimiters at the start of the line belong to the outer level
+        let leading_closers = content
+            .bytes()
+            .take_while(|byte| matches!(byte, b'}' | b']' | b')'))
+            .count();
+        let mut opener_lines: Vec<usize> = layout
+            .blocks
+            .iter()
+            .filter(|(opener, closer, _)| {
+                *opener < position
+                    && (line_of(*closer) > line_index
+                        || (line_of(*closer) == line_index
+                            && *closer >= position + leading_closers))
+            })
+            .map(|(opener, _, _)| line_of(*opener))
+            .collect();
+        opener_lines.sort();
+        opener_lines.dedup();
+
+        lines.push(Line::Code {
+            depth: opener_lines.len(),
+            position,
+            content: content.to_string(),
+            is_comment: layout.comments.contains(&position),
+        });
+    }
+
+    // Align trailing parameter comments of multi line signatures
+    for (opener, closer, _) in layout.blocks.iter().filter(|block| block.2) {
+        let (first_line, last_line) = (line_of(*opener) + 1, line_of(*closer));
+        let mut commented_lines: Vec<(usize, String, String)> = Vec::new();
+        for (line_index, line) in lines.iter().enumerate().take(last_line).skip(first_line) {
+            let Line::Code {
+                position,
+                content,
+                is_comment: false,
+                ..
+            } = line
+            else {
+                continue;
+            };
+            let Some(comment_start) = layout
+                .comments
+                .iter()
+                .find(|start| *position < **start && **start < position + content.len())
+            else {
+                continue;
+            };
+            let (code, comment) = content.split_at(comment_start - position);
+            commented_lines.push((
+                line_index,
+                code.trim_end().to_string(),
+                normalize_comment(comment),
+            ));
+        }
+
+        let width = commented_lines
+            .iter()
+            .map(|(_, code, _)| code.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (line_index, code, comment) in commented_lines {
+            if let Line::Code { content, .. } = &mut lines[line_index] {
+                *content = format!(
+                    "{}{}  {}",
+                    code,
+                    " ".repeat(width - code.chars().count()),
+                    comment
+                );
+            }
+        }
+    }
+
+    // Find doc comments, those are comment lines directly before a def
+    let mut doc_comment_starts: Vec<usize> = Vec::new();
+    for line_index in 0..lines.len() {
+        let depth = match &lines[line_index] {
+            Line::Code {
+                depth,
+                content,
+                is_comment: false,
+                ..
+            } if is_def_line(content) => *depth,
+            _ => continue,
+        };
+        let mut start = line_index;
+        while start > 0
+            && matches!(&lines[start - 1], Line::Code { is_comment: true, depth: comment_depth, .. } if *comment_depth == depth)
+        {
+            start -= 1;
+            if let Line::Code { content, .. } = &mut lines[start] {
+                *content = normalize_comment(content);
+            }
+        }
+        if depth == 0 && start < line_index {
+            doc_comment_starts.push(start);
+        }
+    }
+
+    let mut output: Vec<String> = Vec::new();
+    for (line_index, line) in lines.into_iter().enumerate() {
+        let previous_is_blank = output.last().map_or(true, |line| line.is_empty());
+        match line {
+            Line::Blank => {
+                if !previous_is_blank {
+                    output.push(String::new());
+                }
+            }
+            Line::Verbatim(text) => output.push(text),
+            Line::Code { depth, content, .. } => {
+                if doc_comment_starts.contains(&line_index) && !previous_is_blank {
+                    output.push(String::new());
+                }
+                output.push(format!("{}{}", INDENT.repeat(depth), content));
+            }
+        }
+    }
+    while output.last().is_some_and(|line| line.is_empty()) {
+        output.pop();
+    }
+
+    if output.is_empty() {
+        Some(String::new())
+    } else {
+        Some(format!("{}\n", output.join("\n")))
+    }
+}
+
+/// Parse and flatten the given source, whitespace is removed to be able to compare the result
+fn flatten_source(engine_state: &EngineState, source: &str) -> Vec<(FlatShape, String)> {
+    let mut working_set = StateWorkingSet::new(engine_state);
+    let block = nu_parser::parse(&mut working_set, None, source.as_bytes(), false);
+
+    flatten_block(&working_set, &block)
+        .into_iter()
+        .map(|(span, shape)| {
+            let contents = String::from_utf8_lossy(working_set.get_span_contents(span))
+                .split_whitespace()
+                .collect();
+            (shape, contents)
+        })
+        .collect()
+}
+
+/// Format the given file, returns the new contents if anything changed
+fn format_file<P: AsRef<Path>>(
+    engine_state: &EngineState,
+    file_path: P,
+) -> NurResult<Option<String>> {
+    let file_path = file_path.as_ref();
+    let source = fs::read_to_string(file_path)?;
+
+    let formatted = format_source(&source).ok_or_else(|| {
+        NurError::FormatError(
+            file_path.to_string_lossy().to_string(),
+            String::from("could not tokenize file"),
+        )
+    })?;
+    if formatted == source {
+        return Ok(None);
+    }
+
+    // Make sure we only changed whitespace and never the meaning of the code
+    if flatten_source(engine_state, &source) != flatten_source(engine_state, &formatted) {
+        return Err(NurError::FormatError(
+            file_path.to_string_lossy().to_string(),
+            String::from("formatting would change the parsed code"),
+        ));
+    }
+
+    Ok(Some(formatted))
+}
+
+fn find_format_files(nur_engine: &NurEngine) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = [
+        nur_engine.state.nurfile_path.clone(),
+        nur_engine.state.local_nurfile_path.clone(),
+    ]
+    .into_iter()
+    .filter(|path| path.is_file())
+    .collect();
+
+    if let Ok(entries) = fs::read_dir(&nur_engine.state.config_dir) {
+        let mut config_files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "nu"))
+            .collect();
+        config_files.sort();
+        files.extend(config_files);
+    }
+
+    files
+}
+
+/// Format nurfile, nurfile.local and .nur/*.nu, returns the paths needing formatting
+///
+/// If check_only is set no files will be changed.
+pub(crate) fn format_project(
+    nur_engine: &mut NurEngine,
+    check_only: bool,
+) -> NurResult<Vec<PathBuf>> {
+    let mut changed_paths = Vec::new();
+
+    for file_path in find_format_files(nur_engine) {
+        if let Some(formatted) = format_file(&nur_engine.engine_state, &file_path)? {
+            if !check_only {
+                fs::write(&file_path, formatted)?;
+            }
+            changed_paths.push(file_path);
+        }
+    }
+
+    Ok(changed_paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::init_engine_state;
+    use crate::names::{NUR_CONFIG_DIR, NUR_FILE};
+    use crate::state::NurState;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_format_source_indents_blocks() {
+        assert_eq!(
+            format_source("def \"nur some-task\" [] {\nif true {\n  print \"yes\"\n} else {\n        print \"no\"\n}\n}\n").unwrap(),
+            "def \"nur some-task\" [] {\n    if true {\n        print \"yes\"\n    } else {\n        print \"no\"\n    }\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_source_indents_openers_in_same_line_once() {
+        assert_eq!(
+            format_source("let x = (ls | each {\n|it|\n$it.name\n})\n").unwrap(),
+            "let x = (ls | each {\n    |it|\n    $it.name\n})\n",
+        );
+    }
+
+    #[test]
+    fn test_format_source_keeps_multi_line_strings() {
+        let source = "def \"nur some-task\" [] {\n    print \"some\n  text {  \n\n\n  \"\n}\n";
+
+        assert_eq!(format_source(source).unwrap(), source);
+    }
+
+    #[test]
+    fn test_format_source_aligns_parameters_and_doc_comments() {
+        assert_eq!(
+            format_source("def \"nur other\" [] {}\n#Some task\ndef \"nur some-task\" [\n  name: string #The name\n    --loud(-l)  # Be loud\n] {}\n\n\n\n").unwrap(),
+            "def \"nur other\" [] {}\n\n# Some task\ndef \"nur some-task\" [\n    name: string  # The name\n    --loud(-l)    # Be loud\n] {}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_project() {
+        let temp_dir = tempdir().unwrap();
+        let temp_dir_path = temp_dir.path().to_path_buf();
+        let nurfile_path = temp_dir.path().join(NUR_FILE);
+        fs::write(
+            &nurfile_path,
+            "# Some task\ndef \"nur some-task\" [] {\nprint $nur.task-name\n}\n",
+        )
+        .unwrap();
+        fs::create_dir(temp_dir.path().join(NUR_CONFIG_DIR)).unwrap();
+        let env_path = temp_dir.path().join(NUR_CONFIG_DIR).join("env.nu");
+        fs::write(&env_path, "$env.SOME_VAR = \"value\"\n").unwrap();
+
+        let nur_state = NurState::new(temp_dir_path.clone(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir_path).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+
+        assert_eq!(
+            format_project(&mut nur_engine, true).unwrap(),
+            vec![nurfile_path.clone()]
+        );
+        assert!(fs::read_to_string(&nurfile_path)
+            .unwrap()
+            .contains("\nprint"));
+
+        assert_eq!(
+            format_project(&mut nur_engine, false).unwrap(),
+            vec![nurfile_path.clone()]
+        );
+        assert!(fs::read_to_string(&nurfile_path)
+            .unwrap()
+            .contains("\n    print"));
+        assert!(format_project(&mut nur_engine, true).unwrap().is_empty());
+    }
+}
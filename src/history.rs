@@ -0,0 +1,346 @@
+use crate::engine::NurEngine;
+use crate::errors::{NurError, NurResult};
+use crate::names::NUR_NAME;
+use crate::secrets::mask_secrets;
+use crate::state::NurState;
+use chrono::Local;
+use nu_protocol::PipelineData;
+use nu_utils::escape_quote_string;
+use serde_json::{json, Value as JsonValue};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+fn current_user() -> Option<String> {
+    env::var("USER").or_else(|_| env::var("USERNAME")).ok()
+}
+
+/// Short git revision of the project, git is only called inside of git repositories
+fn git_revision<P: AsRef<Path>>(project_path: P) -> Option<String> {
+    let project_path = project_path.as_ref();
+    if !project_path
+        .ancestors()
+        .any(|path| path.join(".git").exists())
+    {
+        return None;
+    }
+
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|revision| !revision.is_empty())
+}
+
+/// Append the executed task to the run history in .nur/, secrets in the arguments are masked
+/// (those runs are marked as masked and cannot be replayed)
+///
+/// Nothing is recorded in projects without a .nur/ directory, so no untracked files are created.
+pub(crate) fn record_task_run(
+    state: &NurState,
+    exit_code: i32,
+    duration: Duration,
+    secrets: &[String],
+) -> NurResult<()> {
+    let Some(task_name) = &state.task_name else {
+        return Ok(());
+    };
+    if !state.config_dir.is_dir() {
+        return Ok(());
+    }
+    let task_args: Vec<String> = state
+        .task_args()
+        .iter()
+        .map(|arg| mask_secrets(arg, secrets))
+        .collect();
+    let masked = task_args
+        .iter()
+        .zip(state.task_args())
+        .any(|(masked_arg, arg)| masked_arg != arg);
+
+    let entry = json!({
+        "timestamp": Local::now().to_rfc3339(),
+        "task": &task_name[4..],
+        "args": task_args,
+        "masked": masked,
+        "exit_code": exit_code,
+        "duration_ms": duration.as_millis() as u64,
+        "user": current_user(),
+        "git_revision": git_revision(&state.project_path),
+    });
+
+    let mut history_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&state.history_path)?;
+    writeln!(history_file, "{}", entry)?;
+
+    Ok(())
+}
+
+/// Read the unescaped task call of the last recorded task run, including the "nur" prefix
+fn last_task_call(state: &NurState) -> NurResult<Vec<String>> {
+    let contents = fs::read_to_string(&state.history_path).unwrap_or_default();
+    let entry: JsonValue = contents
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str(line).ok())
+        .ok_or(NurError::NoTaskHistory())?;
+
+    // Masked arguments would be replayed as "********"
+    if entry["masked"].as_bool() == Some(true) {
+        return Err(NurError::MaskedTaskHistory(
+            entry["task"].as_str().unwrap_or_default().to_string(),
+        ));
+    }
+
+    let mut task_call = vec![String::from(NUR_NAME)];
+    if let Some(task) = entry["task"].as_str() {
+        task_call.extend(task.split(' ').map(String::from));
+    }
+    if let Some(args) = entry["args"].as_array() {
+        task_call.extend(args.iter().filter_map(|arg| arg.as_str()).map(String::from));
+    }
+    if task_call.len() == 1 {
+        return Err(NurError::NoTaskHistory());
+    }
+
+    Ok(task_call)
+}
+
+/// Check whether the last task call should be replayed (using `--again` or `nur !!`)
+pub(crate) fn wants_replay(state: &NurState) -> bool {
+    state.args_to_nur.iter().any(|arg| arg == "--again")
+        || state.task_call.get(1).is_some_and(|arg| arg == "!!")
+}
+
+/// Replace the task call by the last recorded task call, additional arguments will be appended
+pub(crate) fn replay_last_task_call(state: &mut NurState) -> NurResult<()> {
+    let mut task_call = last_task_call(state)?;
+    let skip_args = if state.raw_task_call.get(1).is_some_and(|arg| arg == "!!") {
+        2
+    } else {
+        1
+    };
+    task_call.extend(state.raw_task_call.iter().skip(skip_args).cloned());
+
+    state.has_task_call = true;
+    state.set_task_call(task_call);
+
+    Ok(())
+}
+
+/// Build the nu pipeline reading the run history, optionally filtered by task and failed runs
+fn history_query<P: AsRef<Path>>(
+    history_path: P,
+    task_filter: Option<&str>,
+    failed_only: bool,
+) -> String {
+    let mut query = format!(
+        "open --raw {} | from json --objects | update timestamp {{ into datetime }} | update duration_ms {{ into duration --unit ms }} | rename --column {{ duration_ms: duration }}",
+        escape_quote_string(&history_path.as_ref().to_string_lossy()),
+    );
+
+    // Filtering by task will include all sub tasks
+    if let Some(task_filter) = task_filter {
+        query.push_str(&format!(
+            " | where {{ |run| $run.task == {} or ($run.task | str starts-with {}) }}",
+            escape_quote_string(task_filter),
+            escape_quote_string(&format!("{} ", task_filter)),
+        ));
+    }
+    if failed_only {
+        query.push_str(" | where exit_code != 0");
+    }
+
+    query
+}
+
+/// Print the run history as a nu table
+pub(crate) fn show_history(
+    nur_engine: &mut NurEngine,
+    task_filter: Option<&str>,
+    failed_only: bool,
+) -> NurResult<i32> {
+    let history_path = nur_engine.state.history_path.clone();
+    if !history_path.exists() {
+        println!("No task runs recorded yet");
+        return Ok(0);
+    }
+
+    nur_engine.eval_and_print(
+        history_query(&history_path, task_filter, failed_only),
+        PipelineData::empty(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::init_engine_state;
+    use crate::names::NUR_FILE;
+    use tempfile::{tempdir, TempDir};
+
+    fn _record_run(temp_dir: &TempDir, task_call: &[&str], task_name: &str, exit_code: i32) {
+        let mut args = vec![String::from("nur")];
+        args.extend(task_call.iter().map(|arg| arg.to_string()));
+        let mut nur_state = NurState::new(temp_dir.path().to_path_buf(), args).unwrap();
+        nur_state.task_name = Some(String::from(task_name));
+
+        let secrets = vec![String::from("secret-value")];
+        record_task_run(&nur_state, exit_code, Duration::from_millis(1500), &secrets).unwrap();
+    }
+
+    #[test]
+    fn test_record_task_run() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        fs::create_dir(temp_dir.path().join(".nur")).unwrap();
+
+        _record_run(
+            &temp_dir,
+            &["some-task", "sub", "arg", "--token=secret-value"],
+            "nur some-task sub",
+            2,
+        );
+
+        let contents = fs::read_to_string(temp_dir.path().join(".nur/history.jsonl")).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(entry["task"], "some-task sub");
+        assert_eq!(entry["args"], json!(["arg", "--token=********"]));
+        assert_eq!(entry["masked"], true);
+        assert_eq!(entry["exit_code"], 2);
+        assert_eq!(entry["duration_ms"], 1500);
+        assert!(entry["timestamp"].is_string());
+        assert_eq!(entry["git_revision"], JsonValue::Null);
+    }
+
+    #[test]
+    fn test_record_task_run_without_config_dir() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+
+        _record_run(&temp_dir, &["build"], "nur build", 0);
+
+        assert!(!temp_dir.path().join(".nur").exists());
+    }
+
+    #[test]
+    fn test_replay_last_task_call() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        fs::create_dir(temp_dir.path().join(".nur")).unwrap();
+        _record_run(&temp_dir, &["build"], "nur build", 0);
+        _record_run(&temp_dir, &["test", "unit", "--fast"], "nur test unit", 1);
+
+        let args = vec![String::from("nur"), String::from("--again")];
+        let mut nur_state = NurState::new(temp_dir.path().to_path_buf(), args).unwrap();
+        assert!(wants_replay(&nur_state));
+        replay_last_task_call(&mut nur_state).unwrap();
+        assert!(nur_state.has_task_call);
+        assert_eq!(nur_state.task_call, vec!["nur", "test", "unit", "--fast"]);
+
+        let args = vec![
+            String::from("nur"),
+            String::from("!!"),
+            String::from("--verbose"),
+        ];
+        let mut nur_state = NurState::new(temp_dir.path().to_path_buf(), args).unwrap();
+        assert!(wants_replay(&nur_state));
+        replay_last_task_call(&mut nur_state).unwrap();
+        assert_eq!(
+            nur_state.task_call,
+            vec!["nur", "test", "unit", "--fast", "--verbose"]
+        );
+    }
+
+    #[test]
+    fn test_replay_last_task_call_with_raw_args() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        fs::create_dir(temp_dir.path().join(".nur")).unwrap();
+        _record_run(&temp_dir, &["greet", "a b"], "nur greet", 0);
+
+        // History entries keep the arguments as passed, replaying escapes them again
+        let contents = fs::read_to_string(temp_dir.path().join(".nur/history.jsonl")).unwrap();
+        let entry: JsonValue = serde_json::from_str(contents.lines().last().unwrap()).unwrap();
+        assert_eq!(entry["args"], json!(["a b"]));
+
+        let args = vec![String::from("nur"), String::from("--again")];
+        let mut nur_state = NurState::new(temp_dir.path().to_path_buf(), args).unwrap();
+        replay_last_task_call(&mut nur_state).unwrap();
+        assert_eq!(nur_state.raw_task_call, vec!["nur", "greet", "a b"]);
+        assert_eq!(nur_state.task_call, vec!["nur", "greet", "\"a b\""]);
+    }
+
+    #[test]
+    fn test_replay_last_task_call_with_masked_args() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        fs::create_dir(temp_dir.path().join(".nur")).unwrap();
+        _record_run(&temp_dir, &["build"], "nur build", 0);
+        _record_run(&temp_dir, &["deploy", "secret-value"], "nur deploy", 0);
+
+        let args = vec![String::from("nur"), String::from("--again")];
+        let mut nur_state = NurState::new(temp_dir.path().to_path_buf(), args).unwrap();
+        assert!(matches!(
+            replay_last_task_call(&mut nur_state),
+            Err(NurError::MaskedTaskHistory(task)) if task == "deploy"
+        ));
+        assert!(!nur_state.has_task_call);
+    }
+
+    #[test]
+    fn test_replay_last_task_call_without_history() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        fs::create_dir(temp_dir.path().join(".nur")).unwrap();
+
+        let args = vec![String::from("nur"), String::from("some-task")];
+        let mut nur_state = NurState::new(temp_dir.path().to_path_buf(), args).unwrap();
+        assert!(!wants_replay(&nur_state));
+        assert!(replay_last_task_call(&mut nur_state).is_err());
+    }
+
+    #[test]
+    fn test_history_query_filters_runs() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        fs::create_dir(temp_dir.path().join(".nur")).unwrap();
+        _record_run(&temp_dir, &["build"], "nur build", 0);
+        _record_run(&temp_dir, &["build", "docs"], "nur build docs", 1);
+        _record_run(&temp_dir, &["builder"], "nur builder", 1);
+        _record_run(&temp_dir, &["test"], "nur test", 0);
+
+        let nur_state =
+            NurState::new(temp_dir.path().to_path_buf(), vec![String::from("nur")]).unwrap();
+        let history_path = nur_state.history_path.clone();
+        let engine_state = init_engine_state(temp_dir.path()).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+        let mut count_runs = |task_filter: Option<&str>, failed_only: bool| {
+            nur_engine
+                .eval_and_capture(format!(
+                    "{} | length",
+                    history_query(&history_path, task_filter, failed_only)
+                ))
+                .unwrap()
+                .value
+                .as_int()
+                .unwrap()
+        };
+
+        assert_eq!(count_runs(None, false), 4);
+        assert_eq!(count_runs(Some("build"), false), 2);
+        assert_eq!(count_runs(None, true), 2);
+        assert_eq!(count_runs(Some("build"), true), 1);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::engine::NurEngine;
+use crate::errors::{NurError, NurResult};
+use crate::names::{
+    NUR_CONFIG_CONFIG_FILENAME, NUR_CONFIG_DIR, NUR_CONFIG_ENV_FILENAME, NUR_CONFIG_LIB_PATH,
+    NUR_FILE, NUR_LOCAL_FILE,
+};
+use crate::nurify::nurify;
+use crate::scripts::{get_default_nur_config, get_default_nur_env, get_default_nurfile};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const GITIGNORE_FILE: &str = ".gitignore";
+const NUR_HISTORY_GITIGNORE_ENTRY: &str = ".nur/history.jsonl";
+const NUR_SECRETS_GITIGNORE_ENTRY: &str = ".nur/secrets.nuon";
+const NUR_LOGS_GITIGNORE_ENTRY: &str = ".nur/logs/";
+const NUR_CACHE_GITIGNORE_ENTRY: &str = ".nur/cache/";
+const NUR_SHELL_GITIGNORE_ENTRY: &str = ".nur/shell/";
+
+/// Write the file unless it already exists
+fn write_new_file<P: AsRef<Path>>(
+    path: P,
+    contents: &str,
+    created_paths: &mut Vec<PathBuf>,
+) -> NurResult<()> {
+    let path = path.as_ref();
+    if path.exists() {
+        return Ok(());
+    }
+
+    fs::write(path, contents)?;
+    created_paths.push(path.to_path_buf());
+
+    Ok(())
+}
+
+fn add_gitignore_entries<P: AsRef<Path>>(
+    path: P,
+    entries: &[&str],
+    created_paths: &mut Vec<PathBuf>,
+) -> NurResult<()> {
+    let path = path.as_ref();
+    let mut contents = if path.exists() {
+        fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+
+    let missing_entries: Vec<&str> = entries
+        .iter()
+        .copied()
+        .filter(|entry| {
+            !contents
+                .lines()
+                .any(|line| line.trim().trim_start_matches('/') == *entry)
+        })
+        .collect();
+    if missing_entries.is_empty() {
+        return Ok(());
+    }
+
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for entry in missing_entries {
+        contents.push_str(entry);
+        contents.push('\n');
+    }
+    fs::write(path, contents)?;
+    created_paths.push(path.to_path_buf());
+
+    Ok(())
+}
+
+/// Create a new nur project in the current directory
+///
+/// This creates the nurfile (optionally converted from another task runner), the .nur/ config
+/// dir including env.nu and config.nu and adds nurfile.local, the run history, the secrets
+/// file, task logs, the task cache and the shell history to .gitignore. An existing nurfile
+/// causes an error unless force is set, all other existing files are always kept.
+pub(crate) fn init_project(
+    nur_engine: &mut NurEngine,
+    from: Option<&str>,
+    force: bool,
+) -> NurResult<Vec<PathBuf>> {
+    let init_path = nur_engine.state.run_path.clone();
+    let nurfile_path = init_path.join(NUR_FILE);
+    let config_dir = init_path.join(NUR_CONFIG_DIR);
+    let mut created_paths = Vec::new();
+
+    if nurfile_path.exists() && !force {
+        return Err(NurError::FileExists(
+            nurfile_path.to_string_lossy().to_string(),
+        ));
+    }
+
+    let nurfile_contents = match from {
+        Some(runner) => nurify(nur_engine, runner, &init_path)?,
+        None => String::from(get_default_nurfile()),
+    };
+    fs::write(&nurfile_path, nurfile_contents)?;
+    created_paths.push(nurfile_path);
+
+    let lib_dir_path = config_dir.join(NUR_CONFIG_LIB_PATH);
+    if !lib_dir_path.exists() {
+        fs::create_dir_all(&lib_dir_path)?;
+        created_paths.push(lib_dir_path);
+    }
+    write_new_file(
+        config_dir.join(NUR_CONFIG_ENV_FILENAME),
+        get_default_nur_env(),
+        &mut created_paths,
+    )?;
+    write_new_file(
+        config_dir.join(NUR_CONFIG_CONFIG_FILENAME),
+        get_default_nur_config(),
+        &mut created_paths,
+    )?;
+
+    add_gitignore_entries(
+        init_path.join(GITIGNORE_FILE),
+        &[
+            NUR_LOCAL_FILE,
+            NUR_HISTORY_GITIGNORE_ENTRY,
+            NUR_SECRETS_GITIGNORE_ENTRY,
+            NUR_LOGS_GITIGNORE_ENTRY,
+            NUR_CACHE_GITIGNORE_ENTRY,
+            NUR_SHELL_GITIGNORE_ENTRY,
+        ],
+        &mut created_paths,
+    )?;
+
+    Ok(created_paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::init_engine_state;
+    use crate::state::NurState;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::{tempdir, TempDir};
+
+    fn _prepare_nur_engine(temp_dir: &TempDir) -> NurEngine {
+        let temp_dir_path = temp_dir.path().to_path_buf();
+        let nur_state = NurState::new(temp_dir_path.clone(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir_path).unwrap();
+
+        NurEngine::new(engine_state, nur_state).unwrap()
+    }
+
+    #[test]
+    fn test_init_project_creates_project_files() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        let created_paths = init_project(&mut nur_engine, None, false).unwrap();
+
+        let temp_dir_path = temp_dir.path();
+        assert_eq!(
+            created_paths,
+            vec![
+                temp_dir_path.join("nurfile"),
+                temp_dir_path.join(".nur/scripts"),
+                temp_dir_path.join(".nur/env.nu"),
+                temp_dir_path.join(".nur/config.nu"),
+                temp_dir_path.join(".gitignore"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir_path.join(NUR_FILE)).unwrap(),
+            get_default_nurfile()
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir_path.join(".nur/env.nu")).unwrap(),
+            get_default_nur_env()
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir_path.join(".gitignore")).unwrap(),
+            "nurfile.local\n.nur/history.jsonl\n.nur/secrets.nuon\n.nur/logs/\n.nur/cache/\n.nur/shell/\n"
+        );
+    }
+
+    #[test]
+    fn test_init_project_keeps_existing_files() {
+        let temp_dir = tempdir().unwrap();
+        let temp_dir_path = temp_dir.path();
+        fs::create_dir(temp_dir_path.join(".nur")).unwrap();
+        fs::write(temp_dir_path.join(".nur/env.nu"), "# custom env").unwrap();
+        fs::write(
+            temp_dir_path.join(".gitignore"),
+            "target/\n/nurfile.local\n.nur/history.jsonl\n.nur/secrets.nuon\n.nur/logs/\n.nur/cache/\n.nur/shell/",
+        )
+        .unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        let created_paths = init_project(&mut nur_engine, None, false).unwrap();
+
+        assert!(!created_paths.contains(&temp_dir_path.join(".nur/env.nu")));
+        assert!(!created_paths.contains(&temp_dir_path.join(".gitignore")));
+        assert_eq!(
+            fs::read_to_string(temp_dir_path.join(".nur/env.nu")).unwrap(),
+            "# custom env"
+        );
+    }
+
+    #[test]
+    fn test_init_project_from_make() {
+        let temp_dir = tempdir().unwrap();
+        let mut makefile = File::create(temp_dir.path().join("Makefile")).unwrap();
+        makefile
+            .write_all(b"# Build it\nbuild:\n\tcc main.c\n")
+            .unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        init_project(&mut nur_engine, Some("make"), false).unwrap();
+
+        let contents = fs::read_to_string(temp_dir.path().join(NUR_FILE)).unwrap();
+        assert!(contents.contains("# Build it\ndef --wrapped \"nur build\" [...args] {"));
+        assert!(contents.contains("^make \"build\" ...$args"));
+    }
+
+    #[test]
+    fn test_init_project_from_npm() {
+        let temp_dir = tempdir().unwrap();
+        let mut package_json = File::create(temp_dir.path().join("package.json")).unwrap();
+        package_json
+            .write_all(b"{\"scripts\": {\"build\": \"tsc\"}}")
+            .unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        init_project(&mut nur_engine, Some("npm"), false).unwrap();
+
+        let contents = fs::read_to_string(temp_dir.path().join(NUR_FILE)).unwrap();
+        assert!(contents.contains("^npm run \"build\" -- ...$args"));
+    }
+
+    #[test]
+    fn test_init_project_will_not_overwrite() {
+        let temp_dir = tempdir().unwrap();
+        let mut makefile = File::create(temp_dir.path().join("Makefile")).unwrap();
+        makefile.write_all(b"build:\n\tcc main.c\n").unwrap();
+        File::create(temp_dir.path().join(NUR_FILE)).unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        assert!(matches!(
+            init_project(&mut nur_engine, Some("make"), false),
+            Err(NurError::FileExists(_))
+        ));
+        assert!(init_project(&mut nur_engine, Some("make"), true).is_ok());
+    }
+
+    #[test]
+    fn test_init_project_force_keeps_config_files() {
+        let temp_dir = tempdir().unwrap();
+        let temp_dir_path = temp_dir.path();
+        fs::write(temp_dir_path.join(NUR_FILE), "# old nurfile").unwrap();
+        fs::create_dir(temp_dir_path.join(".nur")).unwrap();
+        fs::write(temp_dir_path.join(".nur/env.nu"), "# custom env").unwrap();
+        fs::write(temp_dir_path.join(".nur/config.nu"), "# custom config").unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        init_project(&mut nur_engine, None, true).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(temp_dir_path.join(NUR_FILE)).unwrap(),
+            get_default_nurfile()
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir_path.join(".nur/env.nu")).unwrap(),
+            "# custom env"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir_path.join(".nur/config.nu")).unwrap(),
+            "# custom config"
+        );
+    }
+
+    #[test]
+    fn test_init_project_requires_known_runner() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir);
+
+        assert!(matches!(
+            init_project(&mut nur_engine, Some("ant"), false),
+            Err(NurError::UnsupportedTaskRunner(_))
+        ));
+    }
+}
@@ -0,0 +1,61 @@
+//! nur - a taskrunner based on nu shell
+//!
+//! Besides the `nur` binary this crate may be used as a library to embed nur into other
+//! tools. The main entry point is [`NurBuilder`], which discovers the project, sets up the
+//! nu engine and loads all nurfiles:
+//!
+//! ```no_run
+//! use nur::NurBuilder;
+//!
+//! let mut nur_engine = NurBuilder::new().run_path("/path/to/project").build()?;
+//! for task_name in nur_engine.list_tasks() {
+//!     println!("{task_name}");
+//! }
+//! let output = nur_engine.run_task(["hello", "bob"])?;
+//! print!("{}", output.stdout);
+//! println!("{}", output.to_string_lossy());
+//! # Ok::<(), nur::NurError>(())
+//! ```
+
+mod args;
+mod builder;
+mod cache;
+mod capture;
+mod check;
+mod ci;
+mod cli;
+mod commands;
+mod compat;
+mod completions;
+mod confirm;
+mod engine;
+mod errors;
+mod events;
+mod fmt;
+mod history;
+mod init;
+mod logs;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod names;
+mod nu_version;
+mod nurify;
+mod path;
+#[cfg(feature = "plugin")]
+mod plugins;
+mod profile;
+mod prompt;
+mod report;
+mod scripts;
+mod secrets;
+mod shell;
+mod state;
+mod task_dir;
+mod task_env;
+mod task_metadata;
+
+pub use crate::builder::NurBuilder;
+pub use crate::cli::run_cli;
+pub use crate::engine::{NurEngine, NurTaskOutput};
+pub use crate::errors::{NurError, NurResult};
+pub use crate::state::NurState;
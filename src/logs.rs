@@ -0,0 +1,218 @@
+use crate::capture::{OutputStream, OutputTee};
+use crate::errors::NurResult;
+use crate::secrets::mask_secrets;
+use chrono::{Local, NaiveDateTime};
+use nu_utils::strip_ansi_string_likely;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of log files kept for each task, older logs are removed
+const MAX_LOGS_PER_TASK: usize = 20;
+const LOG_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+const LOG_EXTENSION: &str = "log";
+
+/// Writes complete lines without ANSI codes and with all secrets masked, so no unmasked output
+/// ever reaches the log file (the incomplete last line is written when dropped)
+struct CleanLogWriter<W: Write> {
+    writer: W,
+    secrets: Vec<String>,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> CleanLogWriter<W> {
+    fn new(writer: W, secrets: &[String]) -> CleanLogWriter<W> {
+        CleanLogWriter {
+            writer,
+            secrets: secrets.to_vec(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn write_clean(&mut self, data: &[u8]) -> io::Result<()> {
+        let text = strip_ansi_string_likely(String::from_utf8_lossy(data).to_string());
+        self.writer
+            .write_all(mask_secrets(&text, &self.secrets).as_bytes())
+    }
+}
+
+impl<W: Write> Write for CleanLogWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        if let Some(line_end) = self.pending.iter().rposition(|byte| *byte == b'\n') {
+            let lines: Vec<u8> = self.pending.drain(..=line_end).collect();
+            self.write_clean(&lines)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Drop for CleanLogWriter<W> {
+    fn drop(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        let _ = self.write_clean(&pending);
+        let _ = self.writer.flush();
+    }
+}
+
+/// Copies stdout and stderr of the task into a log file in .nur/logs/ while it is running
+pub(crate) struct TaskLog {
+    path: PathBuf,
+    tees: Vec<OutputTee>,
+}
+
+impl TaskLog {
+    /// Create the log file for the task and start copying the output into it, ANSI codes are
+    /// removed and secrets masked before anything is written
+    pub(crate) fn start<P: AsRef<Path>>(
+        logs_dir: P,
+        task_name: &str,
+        secrets: &[String],
+    ) -> NurResult<TaskLog> {
+        let logs_dir = logs_dir.as_ref();
+        fs::create_dir_all(logs_dir)?;
+
+        let log_name = log_name(task_name);
+        let path = logs_dir.join(format!(
+            "{}-{}.{}",
+            log_name,
+            Local::now().format(LOG_TIMESTAMP_FORMAT),
+            LOG_EXTENSION
+        ));
+        // Both streams share the same file, appending keeps their output in order
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let tees = vec![
+            OutputTee::start(
+                OutputStream::Stdout,
+                Box::new(CleanLogWriter::new(file.try_clone()?, secrets)),
+            )?,
+            OutputTee::start(
+                OutputStream::Stderr,
+                Box::new(CleanLogWriter::new(file, secrets)),
+            )?,
+        ];
+
+        prune_logs(logs_dir, &log_name, MAX_LOGS_PER_TASK)?;
+
+        Ok(TaskLog { path, tees })
+    }
+
+    /// Stop copying the output, returns the path of the log
+    pub(crate) fn finish(mut self) -> PathBuf {
+        self.tees.clear();
+
+        self.path
+    }
+}
+
+/// Name of the task usable in file names, like "db-reset" for the task "db reset"
+fn log_name(task_name: &str) -> String {
+    task_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Remove the oldest logs of the task, so only keep_count logs remain
+fn prune_logs(logs_dir: &Path, log_name: &str, keep_count: usize) -> NurResult<()> {
+    let prefix = format!("{}-", log_name);
+    let mut log_files: Vec<String> = fs::read_dir(logs_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|file_name| {
+            // Only match the timestamp, so "db" does not prune the logs of "db-reset"
+            file_name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(&format!(".{}", LOG_EXTENSION)))
+                .is_some_and(|timestamp| {
+                    NaiveDateTime::parse_from_str(timestamp, LOG_TIMESTAMP_FORMAT).is_ok()
+                })
+        })
+        .collect();
+    if log_files.len() <= keep_count {
+        return Ok(());
+    }
+
+    // Timestamps sort chronologically
+    log_files.sort();
+    for file_name in &log_files[..log_files.len() - keep_count] {
+        fs::remove_file(logs_dir.join(file_name))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_log_name() {
+        assert_eq!(log_name("build"), "build");
+        assert_eq!(log_name("db reset"), "db-reset");
+        assert_eq!(log_name("deploy/prod"), "deploy-prod");
+    }
+
+    #[test]
+    fn test_prune_logs() {
+        let temp_dir = tempdir().unwrap();
+        let logs_dir = temp_dir.path();
+        for second in 10..15 {
+            File::create(logs_dir.join(format!("db-20261019-1200{}-000.log", second))).unwrap();
+        }
+        File::create(logs_dir.join("db-reset-20261019-120000-000.log")).unwrap();
+        File::create(logs_dir.join("db-notes.txt")).unwrap();
+
+        prune_logs(logs_dir, "db", 2).unwrap();
+
+        let mut remaining: Vec<String> = fs::read_dir(logs_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            vec![
+                "db-20261019-120013-000.log",
+                "db-20261019-120014-000.log",
+                "db-notes.txt",
+                "db-reset-20261019-120000-000.log",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clean_log_writer() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("build.log");
+        let mut writer = CleanLogWriter::new(
+            File::create(&path).unwrap(),
+            &[String::from("secret-value")],
+        );
+
+        // Secrets split into several writes are only written once the line is complete
+        write!(writer, "\x1b[31mtoken secret-").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        write!(writer, "value\x1b[0m\nlast secret-value").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "token ********\n");
+
+        drop(writer);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "token ********\nlast ********"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use crate::engine::NurEngine;
+use crate::errors::{NurError, NurResult};
+use nu_lsp::LanguageServer;
+use nu_protocol::engine::{EngineState, StateDelta, StateWorkingSet};
+use std::fs;
+use std::path::Path;
+
+/// Parse the given file without reporting errors, returns the changes if it parsed cleanly
+fn parse_file<P: AsRef<Path>>(engine_state: &EngineState, file_path: P) -> Option<StateDelta> {
+    let file_path = file_path.as_ref();
+    let contents = fs::read(file_path).ok()?;
+
+    let mut working_set = StateWorkingSet::new(engine_state);
+    nu_parser::parse(&mut working_set, file_path.to_str(), &contents, false);
+
+    working_set
+        .parse_errors
+        .is_empty()
+        .then(|| working_set.render())
+}
+
+/// Prepare the engine state used by the language server
+///
+/// This contains the `$nur` variable, env and config (including the lib dirs) and all task
+/// definitions. Nurfiles are only parsed, so no task code will be executed. Plugins listed in
+/// .nur/plugins are not registered, only plugins already in the registry are known.
+pub(crate) fn prepare_lsp_engine_state(nur_engine: &mut NurEngine) -> NurResult<EngineState> {
+    if nur_engine.state.has_project_path {
+        // Broken env or config files should not prevent the language server from starting
+        let env_path = nur_engine.state.env_path.clone();
+        if !env_path.exists() || parse_file(&nur_engine.engine_state, &env_path).is_some() {
+            nur_engine.load_env()?;
+        }
+        let config_path = nur_engine.state.config_path.clone();
+        if !config_path.exists() || parse_file(&nur_engine.engine_state, &config_path).is_some() {
+            nur_engine.load_config_without_plugin_registration()?;
+        }
+
+        for nurfile_path in [
+            nur_engine.state.nurfile_path.clone(),
+            nur_engine.state.local_nurfile_path.clone(),
+        ] {
+            if let Some(delta) = parse_file(&nur_engine.engine_state, &nurfile_path) {
+                nur_engine.engine_state.merge_delta(delta)?;
+            }
+        }
+    }
+
+    Ok(nur_engine.engine_state.clone())
+}
+
+/// Run the language server for nurfiles on stdio, returns after the client shut down
+pub(crate) fn run_language_server(nur_engine: &mut NurEngine) -> NurResult<()> {
+    let engine_state = prepare_lsp_engine_state(nur_engine)?;
+
+    LanguageServer::initialize_stdio_connection()
+        .and_then(|language_server| language_server.serve_requests(engine_state))
+        .map_err(|err| NurError::LanguageServerError(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::init_engine_state;
+    use crate::names::{NUR_CONFIG_DIR, NUR_CONFIG_LIB_PATH, NUR_FILE};
+    use crate::state::NurState;
+    use tempfile::{tempdir, TempDir};
+
+    fn _prepare_nur_engine(temp_dir: &TempDir, nurfile: &str) -> NurEngine {
+        let temp_dir_path = temp_dir.path().to_path_buf();
+        fs::write(temp_dir.path().join(NUR_FILE), nurfile).unwrap();
+
+        let nur_state = NurState::new(temp_dir_path.clone(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir_path).unwrap();
+
+        NurEngine::new(engine_state, nur_state).unwrap()
+    }
+
+    fn _parse_errors(engine_state: &EngineState, contents: &str) -> usize {
+        let mut working_set = StateWorkingSet::new(engine_state);
+        nu_parser::parse(&mut working_set, None, contents.as_bytes(), false);
+
+        working_set.parse_errors.len()
+    }
+
+    #[test]
+    fn test_prepare_lsp_engine_state_knows_nur_variable_and_scripts() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine =
+            _prepare_nur_engine(&temp_dir, "# Some task\ndef \"nur some-task\" [] {}");
+        let scripts_dir = temp_dir
+            .path()
+            .join(NUR_CONFIG_DIR)
+            .join(NUR_CONFIG_LIB_PATH);
+        fs::create_dir_all(&scripts_dir).unwrap();
+        fs::write(scripts_dir.join("helper.nu"), "export def greet [] {}").unwrap();
+
+        let engine_state = prepare_lsp_engine_state(&mut nur_engine).unwrap();
+
+        assert_eq!(_parse_errors(&engine_state, "print $nur.project-path"), 0);
+        assert_eq!(
+            _parse_errors(&engine_state, "use helper.nu\nhelper greet"),
+            0
+        );
+        assert!(_parse_errors(&engine_state, "print $unknown") > 0);
+    }
+
+    #[test]
+    fn test_prepare_lsp_engine_state_knows_tasks_without_running_nurfile() {
+        let temp_dir = tempdir().unwrap();
+        let marker_path = temp_dir.path().join("marker");
+        let mut nur_engine = _prepare_nur_engine(
+            &temp_dir,
+            "# Some task\ndef \"nur some-task\" [name: string] {}\ntouch marker",
+        );
+
+        let engine_state = prepare_lsp_engine_state(&mut nur_engine).unwrap();
+
+        let decl_id = engine_state.find_decl(b"nur some-task", &[]).unwrap();
+        let signature = engine_state.get_decl(decl_id).signature();
+        assert_eq!(signature.required_positional[0].name, "name");
+        assert!(!marker_path.exists());
+    }
+
+    #[test]
+    fn test_prepare_lsp_engine_state_ignores_broken_nurfile() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine = _prepare_nur_engine(&temp_dir, "def \"nur broken\" [ {}");
+
+        let engine_state = prepare_lsp_engine_state(&mut nur_engine).unwrap();
+
+        assert!(engine_state.find_decl(b"nur broken", &[]).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "plugin")]
+    fn test_prepare_lsp_engine_state_does_not_register_plugins() {
+        let temp_dir = tempdir().unwrap();
+        let mut nur_engine =
+            _prepare_nur_engine(&temp_dir, "# Some task\ndef \"nur some-task\" [] {}");
+        fs::create_dir_all(temp_dir.path().join(NUR_CONFIG_DIR)).unwrap();
+        fs::write(nur_engine.state.plugins_path.clone(), "nu_plugin_missing\n").unwrap();
+
+        let engine_state = prepare_lsp_engine_state(&mut nur_engine).unwrap();
+
+        assert!(engine_state.find_decl(b"nur some-task", &[]).is_some());
+        assert!(!nur_engine.state.plugin_registry_path.exists());
+    }
+}
@@ -5,17 +5,39 @@ pub(crate) const NUR_CONFIG_DIR: &str = ".nur";
 pub(crate) const NUR_CONFIG_LIB_PATH: &str = "scripts";
 pub(crate) const NUR_CONFIG_CONFIG_FILENAME: &str = "config.nu";
 pub(crate) const NUR_CONFIG_ENV_FILENAME: &str = "env.nu";
+pub(crate) const NUR_CONFIG_HISTORY_FILENAME: &str = "history.jsonl";
+pub(crate) const NUR_CONFIG_SECRETS_FILENAME: &str = "secrets.nuon";
+pub(crate) const NUR_CONFIG_ENCRYPTED_SECRETS_FILENAME: &str = "secrets.nuon.age";
+pub(crate) const NUR_CONFIG_LOGS_DIRNAME: &str = "logs";
+pub(crate) const NUR_CONFIG_CACHE_DIRNAME: &str = "cache";
+pub(crate) const NUR_CONFIG_TASK_CACHE_FILENAME: &str = "tasks.json";
+pub(crate) const NUR_CONFIG_PLUGINS_FILENAME: &str = "plugins";
+#[cfg(feature = "plugin")]
+pub(crate) const NUR_CONFIG_PLUGIN_REGISTRY_FILENAME: &str = "plugin.msgpackz";
+pub(crate) const NUR_CONFIG_SHELL_DIRNAME: &str = "shell";
+
+// nu's own config files, loaded with --use-nu-config
+pub(crate) const NU_CONFIG_ENV_FILENAME: &str = "env.nu";
+pub(crate) const NU_CONFIG_CONFIG_FILENAME: &str = "config.nu";
 
 // $env variable names
 pub(crate) const NUR_ENV_NU_LIB_DIRS: &str = "NU_LIB_DIRS";
 pub(crate) const NUR_ENV_NUR_VERSION: &str = "NUR_VERSION";
 pub(crate) const NUR_ENV_NUR_TASK_CALL: &str = "NUR_TASK_CALL";
 pub(crate) const NUR_ENV_NUR_TASK_NAME: &str = "NUR_TASK_NAME";
+pub(crate) const NUR_ENV_NUR_SECRETS: &str = "NUR_SECRETS";
+pub(crate) const NUR_ENV_NUR_SECRETS_IDENTITY: &str = "NUR_SECRETS_IDENTITY";
 
 // $nur variable names
 pub(crate) const NUR_VAR_RUN_PATH: &str = "run-path";
 pub(crate) const NUR_VAR_PROJECT_PATH: &str = "project-path";
 pub(crate) const NUR_VAR_TASK_NAME: &str = "task-name";
+pub(crate) const NUR_VAR_TASK_PATH: &str = "task-path";
+pub(crate) const NUR_VAR_TASK_ARGS: &str = "task-args";
+pub(crate) const NUR_VAR_FLAGS: &str = "flags";
+pub(crate) const NUR_VAR_IS_TTY: &str = "is-tty";
+pub(crate) const NUR_VAR_NUR_VERSION: &str = "nur-version";
+pub(crate) const NUR_VAR_NU_VERSION: &str = "nu-version";
 pub(crate) const NUR_VAR_CONFIG_DIR: &str = "config-dir";
 pub(crate) const NUR_VAR_DEFAULT_LIB_DIR: &str = "default-lib-dir";
 
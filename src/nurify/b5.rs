@@ -0,0 +1,58 @@
+use crate::nurify::{string_literal, Nurfile, NurifyTask};
+
+fn parse_task_line(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let line = line.strip_prefix("function ").unwrap_or(line).trim_start();
+    let task = line.strip_prefix("task:")?;
+    let end = task.find(|c: char| c == '(' || c == '{' || c.is_whitespace())?;
+
+    Some(&task[..end]).filter(|task| !task.is_empty())
+}
+
+pub(super) fn nurify_from_b5(contents: &str) -> Nurfile {
+    let mut nurfile = Nurfile::default();
+    let mut comment_lines: Vec<&str> = Vec::new();
+
+    for line in contents.lines() {
+        if let Some(comment) = line.trim_start().strip_prefix('#') {
+            if !comment.starts_with('!') {
+                comment_lines.push(comment.trim());
+            }
+            continue;
+        }
+
+        if let Some(task) = parse_task_line(line) {
+            let description = Some(comment_lines.join("\n")).filter(|d| !d.is_empty());
+            let command = format!("^b5 --quiet {}", string_literal(task));
+            nurfile.tasks.push(NurifyTask::passthrough(
+                task.replace(':', " "),
+                description,
+                command,
+            ));
+        }
+
+        comment_lines.clear();
+    }
+
+    nurfile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nurify_from_b5() {
+        let nurfile = nurify_from_b5(
+            "#!/usr/bin/env bash\n\n# Install the project\ntask:install() {\n    npm ci\n}\n\nfunction task:docker:up {\n    docker compose up\n}\n",
+        );
+
+        let names: Vec<&str> = nurfile.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["install", "docker up"]);
+        assert_eq!(
+            nurfile.tasks[0].description,
+            Some(String::from("Install the project"))
+        );
+        assert_eq!(nurfile.tasks[1].command, "^b5 --quiet \"docker:up\"");
+    }
+}
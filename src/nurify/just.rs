@@ -0,0 +1,259 @@
+use crate::nurify::{string_literal, Nurfile, NurifyParam, NurifyParamKind, NurifyTask};
+
+/// Split the recipe header into its words, stops at the ":" ending the recipe parameters
+fn split_recipe_header(header: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut depth = 0;
+    let mut chars = header.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                current.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' | '`' => {
+                    quote = Some(c);
+                    current.push(c);
+                }
+                '(' => {
+                    depth += 1;
+                    current.push(c);
+                }
+                ')' => {
+                    depth -= 1;
+                    current.push(c);
+                }
+                ':' if depth == 0 => {
+                    if chars.peek() == Some(&'=') {
+                        return None; // assignment
+                    }
+                    if !current.is_empty() {
+                        words.push(current);
+                    }
+                    return Some(words);
+                }
+                c if c.is_whitespace() && depth == 0 => {
+                    if !current.is_empty() {
+                        words.push(std::mem::take(&mut current));
+                    }
+                }
+                _ => current.push(c),
+            },
+        }
+    }
+
+    None
+}
+
+fn parse_default(default: &str) -> Option<String> {
+    let default = default.trim();
+    ['\'', '"']
+        .iter()
+        .find_map(|quote| default.strip_prefix(*quote)?.strip_suffix(*quote))
+        .map(string_literal)
+}
+
+fn parse_param(word: &str) -> NurifyParam {
+    let (is_rest, word) = match word.strip_prefix(['+', '*']) {
+        Some(word) => (true, word),
+        None => (false, word),
+    };
+    let word = word.trim_start_matches('$');
+    let (name, default) = match word.split_once('=') {
+        Some((name, default)) => (name, Some(default)),
+        None => (word, None),
+    };
+
+    let kind = if is_rest {
+        NurifyParamKind::Rest
+    } else if default.is_some() {
+        NurifyParamKind::Optional
+    } else {
+        NurifyParamKind::Required
+    };
+    let mut param = NurifyParam::new(name, kind);
+    if !is_rest {
+        param.shape = Some(String::from("string"));
+        param.default = default.and_then(parse_default);
+    }
+
+    param
+}
+
+/// Split the attributes of an attribute line like `[private, doc("a, b")]`
+fn split_attributes(attributes: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+
+    for c in attributes.chars() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                current.push(c);
+            }
+            None => match c {
+                '\'' | '"' => {
+                    quote = Some(c);
+                    current.push(c);
+                }
+                ',' => parts.push(std::mem::take(&mut current)),
+                _ => current.push(c),
+            },
+        }
+    }
+    parts.push(current);
+
+    parts
+        .into_iter()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Get the text of a `doc("...")` attribute, None for other or malformed attributes
+fn parse_doc_attribute(attribute: &str) -> Option<String> {
+    let doc = attribute.trim().strip_prefix("doc(")?.strip_suffix(')')?;
+    let doc = doc.trim();
+
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| doc.strip_prefix(*quote)?.strip_suffix(*quote))
+        .map(String::from)
+}
+
+pub(super) fn nurify_from_just(contents: &str) -> Nurfile {
+    let mut nurfile = Nurfile::default();
+    let mut comment: Option<String> = None;
+    let mut attributes: Vec<String> = Vec::new();
+
+    for line in contents.lines() {
+        if line.starts_with(char::is_whitespace) {
+            continue; // recipe body
+        }
+        if line.trim().is_empty() {
+            comment = None;
+            attributes.clear();
+            continue;
+        }
+        if let Some(line_comment) = line.strip_prefix('#') {
+            if !line_comment.starts_with('!') {
+                comment = Some(line_comment.trim().to_string());
+            }
+            continue;
+        }
+        if let Some(attribute) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            attributes.extend(split_attributes(attribute));
+            continue;
+        }
+        if ["set ", "alias ", "export ", "import", "mod "]
+            .iter()
+            .any(|keyword| line.starts_with(keyword))
+        {
+            comment = None;
+            attributes.clear();
+            continue;
+        }
+
+        if let Some(words) = split_recipe_header(line.trim_start_matches('@')) {
+            if let Some((name, params)) = words.split_first() {
+                let is_private = name.starts_with('_') || attributes.iter().any(|a| a == "private");
+                if !is_private {
+                    let description = attributes
+                        .iter()
+                        .find_map(|a| parse_doc_attribute(a))
+                        .or(comment.clone());
+                    nurfile.tasks.push(NurifyTask {
+                        name: name.clone(),
+                        description,
+                        params: params.iter().map(|p| parse_param(p)).collect(),
+                        command: format!("^just {}", string_literal(name)),
+                        native: false,
+                    });
+                }
+            }
+        }
+
+        comment = None;
+        attributes.clear();
+    }
+
+    nurfile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nurify_from_just() {
+        let nurfile = nurify_from_just(
+            "set shell := [\"bash\", \"-c\"]\nversion := \"1.0\"\n\n# Build the project\nbuild target mode='debug' *flags: deps\n    cargo build\n\n[private]\nhelper:\n    true\n\n_other:\n\n[doc('Run tests')]\n@test:\n    cargo test\n",
+        );
+
+        let names: Vec<&str> = nurfile.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "test"]);
+
+        let build = &nurfile.tasks[0];
+        assert_eq!(build.description, Some(String::from("Build the project")));
+        assert_eq!(build.params.len(), 3);
+        assert_eq!(build.params[0].kind, NurifyParamKind::Required);
+        assert_eq!(build.params[1].kind, NurifyParamKind::Optional);
+        assert_eq!(build.params[1].default, Some(String::from("\"debug\"")));
+        assert_eq!(build.params[2].kind, NurifyParamKind::Rest);
+
+        assert_eq!(
+            nurfile.tasks[1].description,
+            Some(String::from("Run tests"))
+        );
+    }
+
+    #[test]
+    fn test_parse_doc_attribute() {
+        assert_eq!(
+            parse_doc_attribute("doc('Run tests')"),
+            Some(String::from("Run tests"))
+        );
+        assert_eq!(
+            parse_doc_attribute("doc( \"Größe prüfen\" )"),
+            Some(String::from("Größe prüfen"))
+        );
+        assert_eq!(parse_doc_attribute("doc('')"), Some(String::new()));
+    }
+
+    #[test]
+    fn test_parse_doc_attribute_malformed() {
+        assert_eq!(parse_doc_attribute("doc()"), None);
+        assert_eq!(parse_doc_attribute("doc(x)"), None);
+        assert_eq!(parse_doc_attribute("doc(\")"), None);
+        assert_eq!(parse_doc_attribute("doc('unclosed)"), None);
+        assert_eq!(parse_doc_attribute("doc(\"mixed')"), None);
+        assert_eq!(parse_doc_attribute("doc(ü)"), None);
+        assert_eq!(parse_doc_attribute("doc('text'"), None);
+        assert_eq!(parse_doc_attribute("private"), None);
+    }
+
+    #[test]
+    fn test_nurify_from_just_with_malformed_attributes() {
+        let nurfile = nurify_from_just(
+            "# Comment\n[doc()]\nbuild:\n\n[private, doc(\"Test, all\")]\ntest:\n\n[group('ci'), doc(\"Lint, check\")]\nlint:\n\n[doc(ü)]\nfmt:\n",
+        );
+
+        let names: Vec<&str> = nurfile.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "lint", "fmt"]);
+        assert_eq!(nurfile.tasks[0].description, Some(String::from("Comment")));
+        assert_eq!(
+            nurfile.tasks[1].description,
+            Some(String::from("Lint, check"))
+        );
+        assert_eq!(nurfile.tasks[2].description, None);
+    }
+}
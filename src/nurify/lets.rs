@@ -0,0 +1,147 @@
+use crate::nurify::{
+    get_string, string_literal, Nurfile, NurifyParam, NurifyParamKind, NurifyTask,
+};
+use nu_protocol::Value;
+
+fn option_descriptions(options: &str) -> Vec<(String, String)> {
+    options
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with('-'))
+        .filter_map(|line| {
+            let (names, description) = line.split_once("  ")?;
+            let long = names
+                .split([',', ' '])
+                .find(|name| name.starts_with("--"))?
+                .trim_start_matches('-');
+            let long = long.split('=').next().unwrap_or(long);
+            Some((long.to_string(), description.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parse the docopt "Usage:" line of a lets command into params
+fn parse_docopt(command: &str, options: &str) -> Option<Vec<NurifyParam>> {
+    let usage_index = options.find("Usage:")? + "Usage:".len();
+    let usage = options[usage_index..].lines().next()?.trim();
+    let usage = usage.strip_prefix("lets")?.trim_start();
+    let usage = usage.strip_prefix(command)?;
+    let descriptions = option_descriptions(options);
+
+    let mut params = Vec::new();
+    for word in usage.split_whitespace() {
+        let is_optional = word.starts_with('[');
+        let word = word.trim_matches(|c| c == '[' || c == ']');
+        let is_rest = word.ends_with("...");
+        let word = word.trim_end_matches("...");
+
+        let mut param = if let Some(flag) = word.strip_prefix("--") {
+            match flag.split_once('=') {
+                Some((name, _)) => {
+                    let mut param = NurifyParam::new(name, NurifyParamKind::Named);
+                    param.shape = Some(String::from("string"));
+                    param
+                }
+                None => NurifyParam::new(flag, NurifyParamKind::Switch),
+            }
+        } else if let Some(name) = word.strip_prefix('<').and_then(|w| w.strip_suffix('>')) {
+            let kind = match (is_rest, is_optional) {
+                (true, _) => NurifyParamKind::Rest,
+                (false, true) => NurifyParamKind::Optional,
+                (false, false) => NurifyParamKind::Required,
+            };
+            let mut param = NurifyParam::new(name, kind);
+            param.shape = Some(String::from("string"));
+            param
+        } else {
+            continue;
+        };
+
+        param.description = descriptions
+            .iter()
+            .find(|(name, _)| *name == param.name)
+            .map(|(_, description)| description.clone());
+        if !params.iter().any(|p: &NurifyParam| p.name == param.name) {
+            params.push(param);
+        }
+    }
+
+    Some(params)
+}
+
+pub(super) fn nurify_from_lets(lets_config: &Value) -> Nurfile {
+    let mut nurfile = Nurfile::default();
+
+    let Some(commands) = lets_config
+        .as_record()
+        .ok()
+        .and_then(|config| config.get("commands"))
+        .and_then(|commands| commands.as_record().ok())
+    else {
+        return nurfile;
+    };
+
+    for (name, command) in commands.iter() {
+        let description = get_string(command, "description");
+        let mut nurify_task = NurifyTask::passthrough(
+            name.clone(),
+            description,
+            format!("^lets {}", string_literal(name)),
+        );
+        if let Some(params) =
+            get_string(command, "options").and_then(|options| parse_docopt(name, &options))
+        {
+            nurify_task.params = params;
+        }
+
+        nurfile.tasks.push(nurify_task);
+    }
+
+    nurfile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    #[test]
+    fn test_nurify_from_lets() {
+        let lets_config = Value::test_record(record! {
+            "commands" => Value::test_record(record! {
+                "run" => Value::test_record(record! {
+                    "description" => Value::test_string("Run a service"),
+                    "options" => Value::test_string(
+                        "Usage: lets run [--debug] [--level=<level>] <service> [<args>...]\nOptions:\n  --debug, -d      Run with debug output\n  --level=<level>  Log level"
+                    ),
+                }),
+                "test" => Value::test_record(record! {
+                    "cmd" => Value::test_string("go test ./..."),
+                }),
+            }),
+        });
+
+        let nurfile = nurify_from_lets(&lets_config);
+
+        let names: Vec<&str> = nurfile.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["run", "test"]);
+
+        let params = &nurfile.tasks[0].params;
+        let kinds: Vec<&NurifyParamKind> = params.iter().map(|p| &p.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &NurifyParamKind::Switch,
+                &NurifyParamKind::Named,
+                &NurifyParamKind::Required,
+                &NurifyParamKind::Rest
+            ]
+        );
+        assert_eq!(
+            params[0].description,
+            Some(String::from("Run with debug output"))
+        );
+        assert_eq!(params[1].description, Some(String::from("Log level")));
+        assert_eq!(nurfile.tasks[1].params[0].kind, NurifyParamKind::Rest);
+    }
+}
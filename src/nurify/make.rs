@@ -0,0 +1,85 @@
+use crate::nurify::{string_literal, Nurfile, NurifyTask};
+
+fn parse_target_line(line: &str) -> Option<(Vec<String>, Option<String>)> {
+    // Targets need to start at the beginning of the line, special targets are ignored
+    if line.starts_with(|c: char| c.is_whitespace() || c == '.' || c == '#') {
+        return None;
+    }
+
+    let (targets, rest) = line.split_once(':')?;
+    // Skip variable assignments like "VAR := value" or "VAR ::= value"
+    if rest.starts_with('=') || rest.starts_with(":=") || targets.contains('=') {
+        return None;
+    }
+
+    let targets: Vec<String> = targets
+        .split_whitespace()
+        .filter(|target| !target.contains('%') && !target.contains('$'))
+        .map(String::from)
+        .collect();
+    if targets.is_empty() {
+        return None;
+    }
+
+    // Support self documenting Makefiles ("target: deps ## description")
+    let description = rest
+        .split_once("##")
+        .map(|(_, description)| description.trim().to_string())
+        .filter(|description| !description.is_empty());
+
+    Some((targets, description))
+}
+
+pub(super) fn nurify_from_make(contents: &str) -> Nurfile {
+    let mut nurfile = Nurfile::default();
+    let mut comment_lines: Vec<&str> = Vec::new();
+
+    for line in contents.lines() {
+        if let Some(comment) = line.strip_prefix('#') {
+            comment_lines.push(comment.trim_start_matches('#').trim());
+            continue;
+        }
+
+        if let Some((targets, description)) = parse_target_line(line) {
+            let description = description.or_else(|| {
+                Some(comment_lines.join("\n")).filter(|description| !description.is_empty())
+            });
+            for target in targets {
+                if nurfile.tasks.iter().any(|task| task.name == target) {
+                    continue;
+                }
+                let command = format!("^make {}", string_literal(&target));
+                nurfile.tasks.push(NurifyTask::passthrough(
+                    target,
+                    description.clone(),
+                    command,
+                ));
+            }
+        }
+
+        comment_lines.clear();
+    }
+
+    nurfile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nurify_from_make() {
+        let nurfile = nurify_from_make(
+            "VAR := value\n.PHONY: build\n\n# Build the project\nbuild: deps\n\tcc main.c\n\ntest lint: ## Run QA\n\t./qa.sh\n%.o: %.c\n\tcc $<\n",
+        );
+
+        let names: Vec<&str> = nurfile.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "test", "lint"]);
+        assert_eq!(
+            nurfile.tasks[0].description,
+            Some(String::from("Build the project"))
+        );
+        assert_eq!(nurfile.tasks[1].description, Some(String::from("Run QA")));
+        assert_eq!(nurfile.tasks[0].command, "^make \"build\"");
+    }
+}
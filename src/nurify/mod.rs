@@ -0,0 +1,391 @@
+mod b5;
+mod just;
+mod lets;
+mod make;
+mod npm;
+mod task;
+mod toolkit;
+mod tusk;
+
+use crate::engine::NurEngine;
+use crate::errors::{NurError, NurResult};
+use nu_protocol::Value;
+use nu_utils::escape_quote_string;
+use std::path::{Path, PathBuf};
+
+pub(crate) const NURIFY_RUNNERS: [&str; 8] = [
+    "just", "make", "npm", "task", "b5", "lets", "tusk", "toolkit",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NurifyParamKind {
+    Required,
+    Optional,
+    Rest,
+    Named,
+    Switch,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NurifyParam {
+    pub(crate) name: String,
+    pub(crate) kind: NurifyParamKind,
+    pub(crate) shape: Option<String>,
+    pub(crate) short: Option<char>,
+    pub(crate) default: Option<String>, // nu literal, like "\"debug\""
+    pub(crate) description: Option<String>,
+    pub(crate) call: Option<String>, // custom way to pass the param to the original task runner
+}
+
+impl NurifyParam {
+    pub(crate) fn new<S: AsRef<str>>(name: S, kind: NurifyParamKind) -> Self {
+        NurifyParam {
+            name: sanitize_param_name(name.as_ref()),
+            kind,
+            shape: None,
+            short: None,
+            default: None,
+            description: None,
+            call: None,
+        }
+    }
+
+    pub(crate) fn var_name(&self) -> String {
+        format!("${}", self.name.replace('-', "_"))
+    }
+
+    fn render_definition(&self) -> String {
+        let shape = self
+            .shape
+            .as_ref()
+            .map(|shape| format!(": {}", shape))
+            .unwrap_or_default();
+        let default = self
+            .default
+            .as_ref()
+            .map(|default| format!(" = {}", default))
+            .unwrap_or_default();
+        let short = self
+            .short
+            .map(|short| format!(" (-{})", short))
+            .unwrap_or_default();
+        let definition = match self.kind {
+            NurifyParamKind::Required => format!("{}{}", self.name, shape),
+            NurifyParamKind::Optional if self.default.is_some() => {
+                format!("{}{}{}", self.name, shape, default)
+            }
+            NurifyParamKind::Optional => format!("{}?{}", self.name, shape),
+            NurifyParamKind::Rest => format!("...{}{}", self.name, shape),
+            NurifyParamKind::Named => format!("--{}{}{}{}", self.name, short, shape, default),
+            NurifyParamKind::Switch => format!("--{}{}", self.name, short),
+        };
+
+        match &self.description {
+            Some(description) => format!("{}  # {}", definition, single_line(description)),
+            None => definition,
+        }
+    }
+
+    fn render_call(&self, native: bool) -> String {
+        if let Some(call) = &self.call {
+            return call.clone();
+        }
+
+        let var_name = self.var_name();
+        match (&self.kind, native) {
+            (NurifyParamKind::Required, _) => var_name,
+            (NurifyParamKind::Optional, true) => var_name,
+            (NurifyParamKind::Optional, false) if self.default.is_some() => var_name,
+            (NurifyParamKind::Optional, false) => {
+                format!(
+                    "...(if {} != null {{ [{}] }} else {{ [] }})",
+                    var_name, var_name
+                )
+            }
+            (NurifyParamKind::Rest, _) => format!("...{}", var_name),
+            (NurifyParamKind::Named, true) => format!("--{} {}", self.name, var_name),
+            (NurifyParamKind::Named, false) => format!(
+                "...(if {} != null {{ [--{} {}] }} else {{ [] }})",
+                var_name, self.name, var_name
+            ),
+            (NurifyParamKind::Switch, true) => format!("--{}={}", self.name, var_name),
+            (NurifyParamKind::Switch, false) => {
+                format!("...(if {} {{ [--{}] }} else {{ [] }})", var_name, self.name)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NurifyTask {
+    pub(crate) name: String,
+    pub(crate) description: Option<String>,
+    pub(crate) params: Vec<NurifyParam>,
+    pub(crate) command: String, // call to the original task runner, like "^make \"build\""
+    pub(crate) native: bool,    // command is a nu command instead of an external one
+}
+
+impl NurifyTask {
+    /// Task wrapping an external task runner, passing all arguments as is
+    pub(crate) fn passthrough<S: Into<String>>(
+        name: S,
+        description: Option<String>,
+        command: String,
+    ) -> Self {
+        NurifyTask {
+            name: name.into(),
+            description,
+            params: vec![NurifyParam::new("args", NurifyParamKind::Rest)],
+            command,
+            native: false,
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut output = String::new();
+
+        if let Some(description) = &self.description {
+            for line in description.trim().lines() {
+                output.push_str(format!("# {}", line.trim_end()).trim_end());
+                output.push('\n');
+            }
+        }
+
+        let wrapped = if self
+            .params
+            .iter()
+            .any(|param| param.kind == NurifyParamKind::Rest)
+        {
+            " --wrapped"
+        } else {
+            ""
+        };
+        let params = if self.params.is_empty() {
+            String::from("[]")
+        } else if self.params.len() == 1 && self.params[0].description.is_none() {
+            format!("[{}]", self.params[0].render_definition())
+        } else {
+            let param_lines: Vec<String> = self
+                .params
+                .iter()
+                .map(|param| format!("    {}\n", param.render_definition()))
+                .collect();
+            format!("[\n{}]", param_lines.join(""))
+        };
+
+        // Switches and named params are passed first, then positional params
+        let mut call_params: Vec<&NurifyParam> = self.params.iter().collect();
+        call_params.sort_by_key(|param| match param.kind {
+            NurifyParamKind::Named | NurifyParamKind::Switch => 0,
+            NurifyParamKind::Required | NurifyParamKind::Optional => 1,
+            NurifyParamKind::Rest => 2,
+        });
+        let mut call = vec![self.command.clone()];
+        call.extend(
+            call_params
+                .iter()
+                .map(|param| param.render_call(self.native)),
+        );
+
+        output.push_str(&format!(
+            "def{} {} {} {{\n    {}\n}}\n",
+            wrapped,
+            escape_quote_string(&format!("nur {}", self.name)),
+            params,
+            call.join(" "),
+        ));
+
+        output
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Nurfile {
+    pub(crate) header: Vec<String>, // additional code to add before the tasks, like "use" calls
+    pub(crate) tasks: Vec<NurifyTask>,
+}
+
+impl Nurfile {
+    fn render(&self, runner: &str, source_path: &Path) -> String {
+        let mut output = format!(
+            "# nurfile generated by \"nur --init --from {}\" from {}\n# Replace tasks by native nur tasks step by step\n\n",
+            runner,
+            source_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        );
+
+        for line in &self.header {
+            output.push_str(line);
+            output.push('\n');
+        }
+        if !self.header.is_empty() {
+            output.push('\n');
+        }
+
+        let tasks: Vec<String> = self.tasks.iter().map(|task| task.render()).collect();
+        output.push_str(&tasks.join("\n"));
+
+        output
+    }
+}
+
+pub(crate) fn sanitize_param_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    // Avoid clashing with builtin variables
+    match name.as_str() {
+        "env" | "nu" | "nur" | "in" | "it" => format!("{}_", name),
+        _ => name,
+    }
+}
+
+pub(crate) fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Create a nu string literal from the given string
+pub(crate) fn string_literal(value: &str) -> String {
+    escape_quote_string(value)
+}
+
+pub(crate) fn find_file<P: AsRef<Path>>(path: P, candidates: &[&str]) -> NurResult<PathBuf> {
+    candidates
+        .iter()
+        .map(|candidate| path.as_ref().join(candidate))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| NurError::NurifyError(format!("Could not find {}", candidates.join(" or "))))
+}
+
+/// Read structured data (JSON/YAML) using the nu engine
+pub(crate) fn open_data_file(
+    nur_engine: &mut NurEngine,
+    path: &Path,
+    format: &str,
+) -> NurResult<Value> {
+    let output = nur_engine.eval_to_value(format!(
+        "open --raw {} | from {}",
+        string_literal(&path.to_string_lossy()),
+        format,
+    ))?;
+
+    Ok(output.value)
+}
+
+pub(crate) fn get_string(value: &Value, key: &str) -> Option<String> {
+    value
+        .as_record()
+        .ok()?
+        .get(key)?
+        .coerce_string()
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Generate nurfile contents from the task runner config found in path
+pub(crate) fn nurify<P: AsRef<Path>>(
+    nur_engine: &mut NurEngine,
+    runner: &str,
+    path: P,
+) -> NurResult<String> {
+    let path = path.as_ref();
+    let source_path = match runner {
+        "just" => find_file(path, &["justfile", "Justfile", ".justfile"])?,
+        "make" => find_file(path, &["GNUmakefile", "makefile", "Makefile"])?,
+        "npm" => find_file(path, &["package.json"])?,
+        "task" => find_file(
+            path,
+            &[
+                "Taskfile.yml",
+                "taskfile.yml",
+                "Taskfile.yaml",
+                "taskfile.yaml",
+            ],
+        )?,
+        "b5" => find_file(path, &["build/Taskfile"])?,
+        "lets" => find_file(path, &["lets.yaml", "lets.yml"])?,
+        "tusk" => find_file(path, &["tusk.yml", "tusk.yaml"])?,
+        "toolkit" => find_file(path, &["toolkit.nu"])?,
+        _ => return Err(NurError::UnsupportedTaskRunner(String::from(runner))),
+    };
+
+    let nurfile = match runner {
+        "just" => just::nurify_from_just(&std::fs::read_to_string(&source_path)?),
+        "make" => make::nurify_from_make(&std::fs::read_to_string(&source_path)?),
+        "npm" => npm::nurify_from_npm(&open_data_file(nur_engine, &source_path, "json")?),
+        "task" => task::nurify_from_task(&open_data_file(nur_engine, &source_path, "yaml")?),
+        "b5" => b5::nurify_from_b5(&std::fs::read_to_string(&source_path)?),
+        "lets" => lets::nurify_from_lets(&open_data_file(nur_engine, &source_path, "yaml")?),
+        "tusk" => tusk::nurify_from_tusk(&open_data_file(nur_engine, &source_path, "yaml")?),
+        "toolkit" => toolkit::nurify_from_toolkit(nur_engine, &source_path)?,
+        _ => unreachable!(),
+    };
+
+    if nurfile.tasks.is_empty() {
+        return Err(NurError::NurifyError(format!(
+            "Could not find any tasks in {}",
+            source_path.to_string_lossy()
+        )));
+    }
+
+    Ok(nurfile.render(runner, &source_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nurify_task_render_passthrough() {
+        let task = NurifyTask::passthrough(
+            "build",
+            Some(String::from("Build everything")),
+            String::from("^make \"build\""),
+        );
+
+        assert_eq!(
+            task.render(),
+            "# Build everything\ndef --wrapped \"nur build\" [...args] {\n    ^make \"build\" ...$args\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_nurify_task_render_params() {
+        let mut target = NurifyParam::new("target", NurifyParamKind::Required);
+        target.shape = Some(String::from("string"));
+        target.description = Some(String::from("Build target"));
+        let mut mode = NurifyParam::new("build-mode", NurifyParamKind::Optional);
+        mode.default = Some(string_literal("debug"));
+        let verbose = NurifyParam::new("verbose", NurifyParamKind::Switch);
+        let task = NurifyTask {
+            name: String::from("build"),
+            description: None,
+            params: vec![target, mode, verbose],
+            command: String::from("^tool build"),
+            native: false,
+        };
+
+        assert_eq!(
+            task.render(),
+            "def \"nur build\" [\n    target: string  # Build target\n    build-mode = \"debug\"\n    --verbose\n] {\n    ^tool build ...(if $verbose { [--verbose] } else { [] }) $target $build_mode\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_param_name() {
+        assert_eq!(sanitize_param_name("some-name"), "some-name");
+        assert_eq!(sanitize_param_name("some.name"), "some_name");
+        assert_eq!(sanitize_param_name("env"), "env_");
+    }
+}
@@ -0,0 +1,58 @@
+use crate::nurify::{single_line, string_literal, Nurfile, NurifyTask};
+use nu_protocol::Value;
+
+pub(super) fn nurify_from_npm(package: &Value) -> Nurfile {
+    let mut nurfile = Nurfile::default();
+
+    let Some(scripts) = package
+        .as_record()
+        .ok()
+        .and_then(|package| package.get("scripts"))
+        .and_then(|scripts| scripts.as_record().ok())
+    else {
+        return nurfile;
+    };
+
+    for (name, script) in scripts.iter() {
+        let description = script
+            .coerce_string()
+            .ok()
+            .map(|script| format!("npm script: {}", single_line(&script)));
+        let command = format!("^npm run {} --", string_literal(name));
+        nurfile
+            .tasks
+            .push(NurifyTask::passthrough(name.clone(), description, command));
+    }
+
+    nurfile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::{record, Span};
+
+    #[test]
+    fn test_nurify_from_npm() {
+        let package = Value::test_record(record! {
+            "name" => Value::test_string("some-package"),
+            "scripts" => Value::test_record(record! {
+                "build" => Value::test_string("tsc -p ."),
+                "test" => Value::test_string("jest"),
+            }),
+        });
+
+        let nurfile = nurify_from_npm(&package);
+
+        let names: Vec<&str> = nurfile.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "test"]);
+        assert_eq!(
+            nurfile.tasks[0].description,
+            Some(String::from("npm script: tsc -p ."))
+        );
+        assert_eq!(nurfile.tasks[0].command, "^npm run \"build\" --");
+        assert!(nurify_from_npm(&Value::nothing(Span::test_data()))
+            .tasks
+            .is_empty());
+    }
+}
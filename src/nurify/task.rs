@@ -0,0 +1,114 @@
+use crate::nurify::{
+    get_string, string_literal, Nurfile, NurifyParam, NurifyParamKind, NurifyTask,
+};
+use nu_protocol::Value;
+
+fn required_vars(task: &Value) -> Vec<String> {
+    let Some(vars) = task
+        .as_record()
+        .ok()
+        .and_then(|task| task.get("requires"))
+        .and_then(|requires| requires.as_record().ok())
+        .and_then(|requires| requires.get("vars"))
+        .and_then(|vars| vars.as_list().ok())
+    else {
+        return vec![];
+    };
+
+    vars.iter()
+        .filter_map(|var| match var {
+            Value::Record { .. } => get_string(var, "name"),
+            _ => var.coerce_string().ok(),
+        })
+        .collect()
+}
+
+pub(super) fn nurify_from_task(taskfile: &Value) -> Nurfile {
+    let mut nurfile = Nurfile::default();
+
+    let Some(tasks) = taskfile
+        .as_record()
+        .ok()
+        .and_then(|taskfile| taskfile.get("tasks"))
+        .and_then(|tasks| tasks.as_record().ok())
+    else {
+        return nurfile;
+    };
+
+    for (name, task) in tasks.iter() {
+        let is_internal = task
+            .as_record()
+            .ok()
+            .and_then(|task| task.get("internal"))
+            .and_then(|internal| internal.as_bool().ok())
+            .unwrap_or(false);
+        if is_internal {
+            continue;
+        }
+
+        let description = get_string(task, "desc").or_else(|| get_string(task, "summary"));
+        let mut nurify_task = NurifyTask::passthrough(
+            name.clone(),
+            description,
+            format!("^task {}", string_literal(name)),
+        );
+
+        // Required variables are passed like "task some-task VAR=value"
+        let mut params: Vec<NurifyParam> = required_vars(task)
+            .into_iter()
+            .map(|var| {
+                let mut param = NurifyParam::new(var.to_lowercase(), NurifyParamKind::Required);
+                param.shape = Some(String::from("string"));
+                param.description = Some(format!("Value for {}", var));
+                param.call = Some(format!("$\"{}=({})\"", var, param.var_name()));
+                param
+            })
+            .collect();
+        params.append(&mut nurify_task.params);
+        nurify_task.params = params;
+
+        nurfile.tasks.push(nurify_task);
+    }
+
+    nurfile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    #[test]
+    fn test_nurify_from_task() {
+        let taskfile = Value::test_record(record! {
+            "version" => Value::test_string("3"),
+            "tasks" => Value::test_record(record! {
+                "build" => Value::test_record(record! {
+                    "desc" => Value::test_string("Build the project"),
+                    "requires" => Value::test_record(record! {
+                        "vars" => Value::test_list(vec![Value::test_string("TARGET")]),
+                    }),
+                }),
+                "helper" => Value::test_record(record! {
+                    "internal" => Value::test_bool(true),
+                }),
+                "lint" => Value::test_string("golangci-lint run"),
+            }),
+        });
+
+        let nurfile = nurify_from_task(&taskfile);
+
+        let names: Vec<&str> = nurfile.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "lint"]);
+        assert_eq!(
+            nurfile.tasks[0].description,
+            Some(String::from("Build the project"))
+        );
+        assert_eq!(nurfile.tasks[0].params.len(), 2);
+        assert_eq!(nurfile.tasks[0].params[0].name, "target");
+        assert_eq!(
+            nurfile.tasks[0].params[0].call,
+            Some(String::from("$\"TARGET=($target)\""))
+        );
+    }
+}
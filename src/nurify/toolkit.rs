@@ -0,0 +1,142 @@
+use crate::engine::NurEngine;
+use crate::errors::NurResult;
+use crate::nurify::{string_literal, Nurfile, NurifyParam, NurifyParamKind, NurifyTask};
+use nu_protocol::{PipelineData, PositionalArg, SyntaxShape, Value};
+use std::path::Path;
+
+fn shape_to_string(shape: &SyntaxShape) -> Option<String> {
+    match shape {
+        SyntaxShape::Any => None,
+        SyntaxShape::CompleterWrapper(inner, _) => shape_to_string(inner),
+        shape => Some(shape.to_string()),
+    }
+}
+
+fn value_to_literal(value: &Value) -> Option<String> {
+    match value {
+        Value::String { val, .. } => Some(string_literal(val)),
+        Value::Int { val, .. } => Some(val.to_string()),
+        Value::Float { val, .. } => Some(val.to_string()),
+        Value::Bool { val, .. } => Some(val.to_string()),
+        _ => None,
+    }
+}
+
+fn positional_param(arg: &PositionalArg, kind: NurifyParamKind) -> NurifyParam {
+    let mut param = NurifyParam::new(&arg.name, kind);
+    param.shape = shape_to_string(&arg.shape);
+    param.default = arg.default_value.as_ref().and_then(value_to_literal);
+    param.description = Some(arg.desc.clone()).filter(|desc| !desc.is_empty());
+
+    param
+}
+
+pub(super) fn nurify_from_toolkit(nur_engine: &mut NurEngine, path: &Path) -> NurResult<Nurfile> {
+    nur_engine.eval(
+        format!("use {}", string_literal(&path.to_string_lossy())),
+        PipelineData::empty(),
+    )?;
+
+    let mut nurfile = Nurfile {
+        header: vec![String::from("use toolkit.nu")],
+        tasks: vec![],
+    };
+
+    for (name, decl_id) in nur_engine.engine_state.get_decls_sorted(false) {
+        let name = String::from_utf8_lossy(&name).to_string();
+        let Some(command_name) = name.strip_prefix("toolkit ") else {
+            continue;
+        };
+        let decl = nur_engine.engine_state.get_decl(decl_id);
+        let signature = decl.signature();
+
+        let mut params: Vec<NurifyParam> = Vec::new();
+        params.extend(
+            signature
+                .required_positional
+                .iter()
+                .map(|arg| positional_param(arg, NurifyParamKind::Required)),
+        );
+        params.extend(
+            signature
+                .optional_positional
+                .iter()
+                .map(|arg| positional_param(arg, NurifyParamKind::Optional)),
+        );
+        params.extend(
+            signature
+                .named
+                .iter()
+                .filter(|flag| flag.long != "help")
+                .map(|flag| {
+                    let kind = if flag.arg.is_some() {
+                        NurifyParamKind::Named
+                    } else {
+                        NurifyParamKind::Switch
+                    };
+                    let mut param = NurifyParam::new(&flag.long, kind);
+                    param.short = flag.short;
+                    param.shape = flag.arg.as_ref().and_then(shape_to_string);
+                    param.default = flag.default_value.as_ref().and_then(value_to_literal);
+                    param.description = Some(flag.desc.clone()).filter(|desc| !desc.is_empty());
+                    param
+                }),
+        );
+        if let Some(rest) = &signature.rest_positional {
+            params.push(positional_param(rest, NurifyParamKind::Rest));
+        }
+
+        let description = [decl.description(), decl.extra_description()]
+            .iter()
+            .filter(|text| !text.is_empty())
+            .cloned()
+            .collect::<Vec<&str>>()
+            .join("\n\n");
+
+        nurfile.tasks.push(NurifyTask {
+            name: command_name.to_string(),
+            description: Some(description).filter(|description| !description.is_empty()),
+            params,
+            command: name.clone(),
+            native: true,
+        });
+    }
+
+    Ok(nurfile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::NurBuilder;
+    use crate::names::NUR_FILE;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_nurify_from_toolkit() {
+        let temp_dir = tempdir().unwrap();
+        File::create(temp_dir.path().join(NUR_FILE)).unwrap();
+        let toolkit_path = temp_dir.path().join("toolkit.nu");
+        let mut toolkit_file = File::create(&toolkit_path).unwrap();
+        toolkit_file
+            .write_all(
+                b"# Check formatting\nexport def fmt [\n    --check  # Only check\n    path: string = \".\"  # Path to format\n] {}\n",
+            )
+            .unwrap();
+        let mut nur_engine = NurBuilder::new().run_path(temp_dir.path()).build().unwrap();
+
+        let nurfile = nurify_from_toolkit(&mut nur_engine, &toolkit_path).unwrap();
+
+        assert_eq!(nurfile.header, vec![String::from("use toolkit.nu")]);
+        assert_eq!(nurfile.tasks.len(), 1);
+        let task = &nurfile.tasks[0];
+        assert_eq!(task.name, "fmt");
+        assert_eq!(task.command, "toolkit fmt");
+        assert_eq!(task.description, Some(String::from("Check formatting")));
+        assert_eq!(task.params[0].kind, NurifyParamKind::Optional);
+        assert_eq!(task.params[0].default, Some(String::from("\".\"")));
+        assert_eq!(task.params[1].kind, NurifyParamKind::Switch);
+    }
+}
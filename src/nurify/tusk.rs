@@ -0,0 +1,131 @@
+use crate::nurify::{
+    get_string, string_literal, Nurfile, NurifyParam, NurifyParamKind, NurifyTask,
+};
+use nu_protocol::Value;
+
+fn is_private(value: &Value) -> bool {
+    value
+        .as_record()
+        .ok()
+        .and_then(|value| value.get("private"))
+        .and_then(|private| private.as_bool().ok())
+        .unwrap_or(false)
+}
+
+fn get_record_entries<'a>(value: &'a Value, key: &str) -> Vec<(&'a String, &'a Value)> {
+    value
+        .as_record()
+        .ok()
+        .and_then(|value| value.get(key))
+        .and_then(|entries| entries.as_record().ok())
+        .map(|entries| entries.iter().collect())
+        .unwrap_or_default()
+}
+
+fn option_param(name: &str, option: &Value) -> NurifyParam {
+    let option_type = get_string(option, "type").unwrap_or_default();
+    let is_switch = matches!(option_type.as_str(), "bool" | "boolean");
+
+    let mut param = if is_switch {
+        NurifyParam::new(name, NurifyParamKind::Switch)
+    } else {
+        let mut param = NurifyParam::new(name, NurifyParamKind::Named);
+        param.shape = Some(String::from("string"));
+        param.default = get_string(option, "default").map(|default| string_literal(&default));
+        param
+    };
+    param.short = get_string(option, "short").and_then(|short| {
+        let mut chars = short.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    });
+    param.description = get_string(option, "usage");
+
+    param
+}
+
+pub(super) fn nurify_from_tusk(tusk_config: &Value) -> Nurfile {
+    let mut nurfile = Nurfile::default();
+
+    for (name, task) in get_record_entries(tusk_config, "tasks") {
+        if is_private(task) {
+            continue;
+        }
+
+        let description = get_string(task, "description").or_else(|| get_string(task, "usage"));
+        let mut params: Vec<NurifyParam> = get_record_entries(task, "args")
+            .into_iter()
+            .map(|(arg_name, arg)| {
+                let mut param = NurifyParam::new(arg_name, NurifyParamKind::Required);
+                param.shape = Some(String::from("string"));
+                param.description = get_string(arg, "usage");
+                param
+            })
+            .collect();
+        params.extend(
+            get_record_entries(task, "options")
+                .into_iter()
+                .filter(|(_, option)| !is_private(option))
+                .map(|(option_name, option)| option_param(option_name, option)),
+        );
+
+        nurfile.tasks.push(NurifyTask {
+            name: name.clone(),
+            description,
+            params,
+            command: format!("^tusk {}", string_literal(name)),
+            native: false,
+        });
+    }
+
+    nurfile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    #[test]
+    fn test_nurify_from_tusk() {
+        let tusk_config = Value::test_record(record! {
+            "tasks" => Value::test_record(record! {
+                "greet" => Value::test_record(record! {
+                    "usage" => Value::test_string("Say hello"),
+                    "args" => Value::test_record(record! {
+                        "name" => Value::test_record(record! {
+                            "usage" => Value::test_string("The person to greet"),
+                        }),
+                    }),
+                    "options" => Value::test_record(record! {
+                        "loud" => Value::test_record(record! {
+                            "type" => Value::test_string("bool"),
+                            "short" => Value::test_string("l"),
+                        }),
+                        "greeting" => Value::test_record(record! {
+                            "default" => Value::test_string("Hello"),
+                        }),
+                    }),
+                }),
+                "internal" => Value::test_record(record! {
+                    "private" => Value::test_bool(true),
+                }),
+            }),
+        });
+
+        let nurfile = nurify_from_tusk(&tusk_config);
+
+        assert_eq!(nurfile.tasks.len(), 1);
+        let task = &nurfile.tasks[0];
+        assert_eq!(task.name, "greet");
+        assert_eq!(task.description, Some(String::from("Say hello")));
+        assert_eq!(task.params[0].kind, NurifyParamKind::Required);
+        assert_eq!(
+            task.params[0].description,
+            Some(String::from("The person to greet"))
+        );
+        assert_eq!(task.params[1].kind, NurifyParamKind::Switch);
+        assert_eq!(task.params[1].short, Some('l'));
+        assert_eq!(task.params[2].kind, NurifyParamKind::Named);
+        assert_eq!(task.params[2].default, Some(String::from("\"Hello\"")));
+    }
+}
@@ -40,6 +40,7 @@ pub(crate) fn find_project_path<P: AsRef<Path>>(cwd: P) -> Option<PathBuf> {
 }
 
 #[cfg(test)]
+#[allow(clippy::single_match)]
 mod tests {
     use super::*;
     use std::fs::{create_dir, File};
@@ -0,0 +1,109 @@
+use crate::errors::NurResult;
+use crate::state::NurState;
+use nu_protocol::PluginRegistryFile;
+use std::fs;
+use std::path::PathBuf;
+
+/// Plugin executables listed in .nur/plugins, one per line and relative to the project path.
+/// Empty lines and lines starting with "#" are ignored.
+pub(crate) fn read_plugin_list(nur_state: &NurState) -> NurResult<Vec<PathBuf>> {
+    if !nur_state.plugins_path.exists() {
+        return Ok(vec![]);
+    }
+
+    Ok(fs::read_to_string(&nur_state.plugins_path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| nu_path::expand_path_with(line, &nur_state.project_path, true))
+        .collect())
+}
+
+/// Plugins missing from the project plugin registry or changed since they were registered
+pub(crate) fn plugins_to_register(nur_state: &NurState, plugins: &[PathBuf]) -> Vec<PathBuf> {
+    let registry = fs::File::open(&nur_state.plugin_registry_path)
+        .ok()
+        .and_then(|mut file| PluginRegistryFile::read_from(&mut file, None).ok());
+    let registry_modified = fs::metadata(&nur_state.plugin_registry_path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    plugins
+        .iter()
+        .filter(|plugin| {
+            let (Some(registry), Some(registry_modified)) = (&registry, registry_modified) else {
+                return true;
+            };
+            let filename = fs::canonicalize(plugin).unwrap_or(plugin.to_path_buf());
+            let is_registered = registry
+                .plugins
+                .iter()
+                .any(|item| item.filename == filename);
+            let is_changed = fs::metadata(&filename)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified > registry_modified);
+
+            !is_registered || is_changed
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::names::NUR_FILE;
+    use nu_protocol::{PluginRegistryItem, PluginRegistryItemData};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plugins_to_register() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        fs::create_dir_all(temp_dir.path().join(".nur/bin")).unwrap();
+        fs::write(
+            temp_dir.path().join(".nur/plugins"),
+            "# Project plugins\n.nur/bin/nu_plugin_one\n\n  .nur/bin/nu_plugin_two  \n",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join(".nur/bin/nu_plugin_one"), "").unwrap();
+        fs::write(temp_dir.path().join(".nur/bin/nu_plugin_two"), "").unwrap();
+
+        let nur_state =
+            NurState::new(temp_dir.path().to_path_buf(), vec![String::from("nur")]).unwrap();
+        let plugins = read_plugin_list(&nur_state).unwrap();
+        assert_eq!(
+            plugins,
+            vec![
+                temp_dir.path().join(".nur/bin/nu_plugin_one"),
+                temp_dir.path().join(".nur/bin/nu_plugin_two"),
+            ]
+        );
+
+        // Without a registry all plugins need to be registered
+        assert_eq!(plugins_to_register(&nur_state, &plugins), plugins);
+
+        // Registered plugins are skipped
+        let mut registry = PluginRegistryFile::new();
+        registry.upsert_plugin(PluginRegistryItem {
+            name: String::from("one"),
+            filename: fs::canonicalize(&plugins[0]).unwrap(),
+            shell: None,
+            data: PluginRegistryItemData::Valid {
+                metadata: Default::default(),
+                commands: vec![],
+            },
+        });
+        fs::create_dir_all(nur_state.plugin_registry_path.parent().unwrap()).unwrap();
+        registry
+            .write_to(
+                fs::File::create(&nur_state.plugin_registry_path).unwrap(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            plugins_to_register(&nur_state, &plugins),
+            vec![plugins[1].clone()]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Measures the time spent in each phase of the nur startup, shown by `--profile-startup`
+#[derive(Debug)]
+pub(crate) struct StartupProfile {
+    started_at: Instant,
+    phase_started_at: Instant,
+    phases: Vec<(String, Duration)>,
+}
+
+impl Default for StartupProfile {
+    fn default() -> Self {
+        StartupProfile::new()
+    }
+}
+
+impl StartupProfile {
+    pub(crate) fn new() -> StartupProfile {
+        let now = Instant::now();
+
+        StartupProfile {
+            started_at: now,
+            phase_started_at: now,
+            phases: vec![],
+        }
+    }
+
+    /// End the current phase, the next phase starts now
+    pub(crate) fn phase_done<S: Into<String>>(&mut self, name: S) {
+        let now = Instant::now();
+        self.phases
+            .push((name.into(), now.duration_since(self.phase_started_at)));
+        self.phase_started_at = now;
+    }
+
+    fn report(&self) -> String {
+        let name_width = self
+            .phases
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("total".len());
+
+        let mut report = String::new();
+        for (name, duration) in &self.phases {
+            report.push_str(&format!(
+                "{:<width$}  {:>8.2} ms\n",
+                name,
+                duration.as_secs_f64() * 1000.0,
+                width = name_width
+            ));
+        }
+        report.push_str(&format!(
+            "{:<width$}  {:>8.2} ms\n",
+            "total",
+            self.phases
+                .iter()
+                .map(|(_, duration)| *duration)
+                .sum::<Duration>()
+                .as_secs_f64()
+                * 1000.0,
+            width = name_width
+        ));
+
+        report
+    }
+
+    /// Print the time spent in all phases to stderr
+    pub(crate) fn print(&self) {
+        let mut stderr = io::stderr().lock();
+        let _ = writeln!(
+            stderr,
+            "Startup profile ({:.2} ms since start):",
+            self.started_at.elapsed().as_secs_f64() * 1000.0
+        );
+        let _ = write!(stderr, "{}", self.report());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_profile_report() {
+        let mut profile = StartupProfile::new();
+        profile.phase_done("nu engine");
+        profile.phase_done("nurfiles");
+
+        let report = profile.report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("nu engine "));
+        assert!(lines[1].starts_with("nurfiles  "));
+        assert!(lines[2].starts_with("total     "));
+        assert!(lines[2].ends_with(" ms"));
+    }
+}
@@ -0,0 +1,238 @@
+use crate::errors::{NurError, NurResult};
+use nu_protocol::{PositionalArg, Signature, SyntaxShape};
+use reedline::{
+    default_emacs_keybindings, ColumnarMenu, Completer, DefaultPrompt, DefaultPromptSegment, Emacs,
+    KeyCode, KeyModifiers, MenuBuilder, Reedline, ReedlineEvent, ReedlineMenu, Signal, Span,
+    Suggestion,
+};
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+/// Completes file and directory names, relative paths are based on the project path
+struct PathCompleter {
+    base_path: PathBuf,
+}
+
+impl Completer for PathCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        let typed = &line[..pos];
+        let (dir_part, file_prefix) = match typed.rfind(['/', MAIN_SEPARATOR]) {
+            Some(index) => (&typed[..=index], &typed[index + 1..]),
+            None => ("", typed),
+        };
+        let Ok(entries) = fs::read_dir(self.base_path.join(dir_part)) else {
+            return vec![];
+        };
+
+        let mut suggestions: Vec<Suggestion> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !name.starts_with(file_prefix) {
+                    return None;
+                }
+                let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+
+                Some(Suggestion {
+                    value: format!("{}{}{}", dir_part, name, if is_dir { "/" } else { "" }),
+                    span: Span::new(0, pos),
+                    append_whitespace: false,
+                    ..Suggestion::default()
+                })
+            })
+            .collect();
+        suggestions.sort_by(|a, b| a.value.cmp(&b.value));
+
+        suggestions
+    }
+}
+
+fn is_flag(arg: &str) -> bool {
+    arg.starts_with('-') && arg.len() > 1 && arg[1..].parse::<f64>().is_err()
+}
+
+/// Count the positional arguments passed to the task, flag values are not counted
+fn count_positionals(signature: &Signature, task_args: &[String]) -> usize {
+    let mut count = 0;
+    let mut args = task_args.iter();
+    while let Some(arg) = args.next() {
+        if !is_flag(arg) {
+            count += 1;
+            continue;
+        }
+
+        let takes_value = if let Some(long_name) = arg.strip_prefix("--") {
+            !long_name.contains('=')
+                && signature
+                    .get_long_flag(long_name)
+                    .is_some_and(|flag| flag.arg.is_some())
+        } else {
+            // Short flags may be combined, only the last one may take a value
+            arg.chars().last().is_some_and(|short_name| {
+                signature
+                    .get_short_flag(short_name)
+                    .is_some_and(|flag| flag.arg.is_some())
+            })
+        };
+        if takes_value {
+            args.next();
+        }
+    }
+
+    count
+}
+
+/// Get all required positional params of the task which were not passed
+pub(crate) fn missing_positionals(
+    signature: &Signature,
+    task_args: &[String],
+) -> Vec<PositionalArg> {
+    signature
+        .required_positional
+        .iter()
+        .skip(count_positionals(signature, task_args))
+        .cloned()
+        .collect()
+}
+
+fn prompt_for_value<P: AsRef<Path>>(
+    positional: &PositionalArg,
+    base_path: P,
+) -> NurResult<Option<String>> {
+    if positional.desc.is_empty() {
+        eprintln!("{} <{}>", positional.name, positional.shape);
+    } else {
+        eprintln!(
+            "{} <{}>: {}",
+            positional.name, positional.shape, positional.desc
+        );
+    }
+
+    let mut line_editor = Reedline::create();
+    if matches!(
+        positional.shape,
+        SyntaxShape::Filepath | SyntaxShape::Directory | SyntaxShape::GlobPattern
+    ) {
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::UntilFound(vec![
+                ReedlineEvent::Menu(String::from("completion_menu")),
+                ReedlineEvent::MenuNext,
+            ]),
+        );
+        line_editor = line_editor
+            .with_completer(Box::new(PathCompleter {
+                base_path: base_path.as_ref().to_path_buf(),
+            }))
+            .with_menu(ReedlineMenu::EngineCompleter(Box::new(
+                ColumnarMenu::default().with_name("completion_menu"),
+            )))
+            .with_edit_mode(Box::new(Emacs::new(keybindings)));
+    }
+
+    let prompt = DefaultPrompt::new(
+        DefaultPromptSegment::Basic(positional.name.clone()),
+        DefaultPromptSegment::Empty,
+    );
+    loop {
+        match line_editor
+            .read_line(&prompt)
+            .map_err(|err| NurError::IoError(err.to_string()))?
+        {
+            Signal::Success(value) if value.is_empty() => continue,
+            Signal::Success(value) => return Ok(Some(value)),
+            Signal::CtrlC | Signal::CtrlD => return Ok(None),
+        }
+    }
+}
+
+/// Ask for the values of all missing required params, returns the values to append to the task
+/// call. Prompting stops when aborted (Ctrl+C or Ctrl+D).
+pub(crate) fn prompt_missing_positionals<P: AsRef<Path>>(
+    signature: &Signature,
+    task_args: &[String],
+    base_path: P,
+) -> NurResult<Vec<String>> {
+    let mut values = Vec::new();
+    for positional in missing_positionals(signature, task_args) {
+        match prompt_for_value(&positional, base_path.as_ref())? {
+            Some(value) => values.push(value),
+            None => break,
+        }
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn _signature() -> Signature {
+        Signature::build("nur some-task")
+            .required("first", SyntaxShape::String, "first param")
+            .required("second", SyntaxShape::Int, "second param")
+            .optional("third", SyntaxShape::String, "third param")
+            .named("name", SyntaxShape::String, "some named flag", Some('n'))
+            .switch("fast", "some switch", Some('f'))
+    }
+
+    fn _args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn _missing_names(args: &[&str]) -> Vec<String> {
+        missing_positionals(&_signature(), &_args(args))
+            .into_iter()
+            .map(|positional| positional.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_missing_positionals() {
+        assert_eq!(_missing_names(&[]), vec!["first", "second"]);
+        assert_eq!(_missing_names(&["a"]), vec!["second"]);
+        assert_eq!(_missing_names(&["a", "-5"]), Vec::<String>::new());
+        assert_eq!(_missing_names(&["a", "1", "b"]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_missing_positionals_skips_flags_and_their_values() {
+        assert_eq!(_missing_names(&["--name", "a"]), vec!["first", "second"]);
+        assert_eq!(_missing_names(&["--name=a", "b"]), vec!["second"]);
+        assert_eq!(_missing_names(&["-fn", "a", "b"]), vec!["second"]);
+        assert_eq!(_missing_names(&["--fast", "a"]), vec!["second"]);
+    }
+
+    #[test]
+    fn test_path_completer() {
+        let temp_dir = tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join("some-dir")).unwrap();
+        fs::write(temp_dir.path().join("some-dir/file.txt"), "").unwrap();
+        fs::write(temp_dir.path().join("some-file"), "").unwrap();
+        fs::write(temp_dir.path().join("other"), "").unwrap();
+
+        let mut completer = PathCompleter {
+            base_path: temp_dir.path().to_path_buf(),
+        };
+        let values = |completer: &mut PathCompleter, line: &str| {
+            completer
+                .complete(line, line.len())
+                .into_iter()
+                .map(|suggestion| suggestion.value)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            values(&mut completer, "some"),
+            vec!["some-dir/", "some-file"]
+        );
+        assert_eq!(
+            values(&mut completer, "some-dir/"),
+            vec!["some-dir/file.txt"]
+        );
+    }
+}
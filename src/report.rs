@@ -0,0 +1,191 @@
+use crate::errors::{NurError, NurResult};
+use chrono::Local;
+use nu_utils::strip_ansi_string_likely;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Result of an executed task, used to write reports
+#[derive(Clone, Debug)]
+pub(crate) struct TaskResult {
+    pub name: String,
+    pub exit_code: i32,
+    pub duration: Duration,
+    pub stderr: String,
+}
+
+/// Results of sub tasks, collected while the task is running
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TaskResults {
+    results: Arc<Mutex<Vec<TaskResult>>>,
+}
+
+impl TaskResults {
+    pub(crate) fn new() -> TaskResults {
+        TaskResults::default()
+    }
+
+    pub(crate) fn push(&self, result: TaskResult) {
+        let mut results = self.results.lock().unwrap_or_else(|err| err.into_inner());
+        results.push(result);
+    }
+
+    /// All results collected so far, in the order the tasks finished
+    pub(crate) fn take(&self) -> Vec<TaskResult> {
+        let mut results = self.results.lock().unwrap_or_else(|err| err.into_inner());
+        std::mem::take(&mut *results)
+    }
+}
+
+/// Report requested by `--report <format>=<path>`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Report {
+    JUnit(PathBuf),
+}
+
+impl Report {
+    /// Parse the report argument, relative paths are based on the given path
+    pub(crate) fn parse<P: AsRef<Path>>(argument: &str, base_path: P) -> NurResult<Report> {
+        let Some((format, path)) = argument.split_once('=') else {
+            return Err(NurError::InvalidReport(String::from(argument)));
+        };
+        if path.is_empty() {
+            return Err(NurError::InvalidReport(String::from(argument)));
+        }
+        let path = base_path.as_ref().join(path);
+
+        match format {
+            "junit" => Ok(Report::JUnit(path)),
+            _ => Err(NurError::InvalidReport(String::from(argument))),
+        }
+    }
+
+    /// Write the report for all task results, sub tasks are part of the total duration
+    pub(crate) fn write(
+        &self,
+        suite_name: &str,
+        duration: Duration,
+        results: &[TaskResult],
+    ) -> NurResult<()> {
+        match self {
+            Report::JUnit(path) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, junit_xml(suite_name, duration, results))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Other control characters are not allowed in XML
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn junit_xml(suite_name: &str, duration: Duration, results: &[TaskResult]) -> String {
+    let failures = results
+        .iter()
+        .filter(|result| result.exit_code != 0)
+        .count();
+    let total_time = duration.as_secs_f64();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"nur\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        results.len(),
+        failures,
+        total_time,
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{:.3}\" timestamp=\"{}\">\n",
+        escape_xml(suite_name),
+        results.len(),
+        failures,
+        total_time,
+        Local::now().format("%Y-%m-%dT%H:%M:%S"),
+    ));
+    for result in results {
+        xml.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"nur\" time=\"{:.3}\"",
+            escape_xml(&result.name),
+            result.duration.as_secs_f64(),
+        ));
+        if result.exit_code == 0 {
+            xml.push_str("/>\n");
+            continue;
+        }
+
+        xml.push_str(">\n");
+        xml.push_str(&format!(
+            "      <failure message=\"Task failed with exit code {}\" type=\"exit code {}\">{}</failure>\n",
+            result.exit_code,
+            result.exit_code,
+            escape_xml(&strip_ansi_string_likely(result.stderr.clone())),
+        ));
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n");
+    xml.push_str("</testsuites>\n");
+
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report() {
+        assert_eq!(
+            Report::parse("junit=reports/nur.xml", "/project").unwrap(),
+            Report::JUnit(PathBuf::from("/project/reports/nur.xml"))
+        );
+        assert!(Report::parse("junit", "/project").is_err());
+        assert!(Report::parse("junit=", "/project").is_err());
+        assert!(Report::parse("html=report.html", "/project").is_err());
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        let results = vec![
+            TaskResult {
+                name: String::from("build"),
+                exit_code: 0,
+                duration: Duration::from_millis(1500),
+                stderr: String::new(),
+            },
+            TaskResult {
+                name: String::from("qa lint"),
+                exit_code: 2,
+                duration: Duration::from_millis(250),
+                stderr: String::from("\x1b[31mfound <3> problems\x1b[0m\n"),
+            },
+        ];
+
+        let xml = junit_xml("project", Duration::from_millis(1750), &results);
+        assert!(xml.contains("<testsuites name=\"nur\" tests=\"2\" failures=\"1\" time=\"1.750\">"));
+        assert!(xml.contains("<testcase name=\"build\" classname=\"nur\" time=\"1.500\"/>"));
+        assert!(xml.contains("<testcase name=\"qa lint\" classname=\"nur\" time=\"0.250\">"));
+        assert!(xml.contains(
+            "<failure message=\"Task failed with exit code 2\" type=\"exit code 2\">found &lt;3&gt; problems\n</failure>"
+        ));
+    }
+}
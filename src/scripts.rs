@@ -5,3 +5,11 @@ pub(crate) fn get_default_nur_env() -> &'static str {
 pub(crate) fn get_default_nur_config() -> &'static str {
     include_str!("nu-scripts/default_nur_config.nu")
 }
+
+pub(crate) fn get_nur_shell_setup() -> &'static str {
+    include_str!("nu-scripts/nur_shell.nu")
+}
+
+pub(crate) fn get_default_nurfile() -> &'static str {
+    include_str!("nu-scripts/default_nurfile.nu")
+}
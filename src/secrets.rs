@@ -0,0 +1,412 @@
+#[cfg(unix)]
+use crate::capture::OutputStream;
+use crate::capture::OutputTee;
+use crate::engine::NurEngine;
+use crate::errors::{NurError, NurResult};
+use crate::names::NUR_ENV_NUR_SECRETS;
+use age::armor::ArmoredReader;
+use age::{Decryptor, IdentityFile};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+const SECRET_MASK: &[u8] = b"********";
+// Shorter values would mask too much of the normal output
+const MIN_SECRET_LENGTH: usize = 4;
+
+/// The secrets file is not encrypted, so it must only be readable by its owner
+pub(crate) fn check_secrets_file_permissions<P: AsRef<Path>>(secrets_path: P) -> NurResult<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(&secrets_path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(NurError::InsecureSecretsFile(
+                secrets_path.as_ref().to_string_lossy().to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Decrypt the secrets file using the age identity file, the secrets file may be ASCII armored
+pub(crate) fn decrypt_secrets_file<P: AsRef<Path>>(
+    secrets_path: P,
+    identity_path: Option<String>,
+) -> NurResult<String> {
+    let secrets_path = secrets_path.as_ref().to_string_lossy().to_string();
+    let decryption_error =
+        |err: &dyn Display| NurError::SecretsDecryptionError(secrets_path.clone(), err.to_string());
+    let identity_path =
+        identity_path.ok_or_else(|| NurError::MissingSecretsIdentity(secrets_path.clone()))?;
+
+    let identities = IdentityFile::from_file(identity_path)
+        .map_err(|err| decryption_error(&err))?
+        .into_identities()
+        .map_err(|err| decryption_error(&err))?;
+    let encrypted = ArmoredReader::new(BufReader::new(File::open(&secrets_path)?));
+    let mut reader = Decryptor::new(encrypted)
+        .and_then(|decryptor| {
+            decryptor.decrypt(identities.iter().map(|identity| identity.as_ref() as _))
+        })
+        .map_err(|err| decryption_error(&err))?;
+
+    let mut secrets = String::new();
+    reader
+        .read_to_string(&mut secrets)
+        .map_err(|err| decryption_error(&err))?;
+
+    Ok(secrets)
+}
+
+/// Nu script to load the secrets (nuon record given as input) into $env, marking all loaded
+/// variables as secret
+pub(crate) fn load_secrets_script() -> String {
+    format!(
+        "let secrets = ($in | from nuon)\nload-env $secrets\n$env.{} = ({} | append ($secrets | columns) | uniq)",
+        NUR_ENV_NUR_SECRETS,
+        secret_names_expression(),
+    )
+}
+
+/// Nu expression returning the names of all secret env vars, $env.NUR_SECRETS may be a list
+/// or a comma separated string (when set outside of nu)
+fn secret_names_expression() -> String {
+    format!(
+        "[($env.{}? | default [])] | flatten | each {{ split row \",\" }} | flatten | str trim | where $it != \"\"",
+        NUR_ENV_NUR_SECRETS,
+    )
+}
+
+/// Get the values of all env vars marked as secret
+pub(crate) fn secret_values(nur_engine: &mut NurEngine) -> NurResult<Vec<String>> {
+    let output = nur_engine.eval_to_value(format!(
+        "{} | each {{ |name| $env | get -i $name }} | compact | each {{ into string }}",
+        secret_names_expression(),
+    ))?;
+
+    let mut secrets: Vec<String> = output
+        .value
+        .as_list()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().ok())
+                .filter(|value| value.len() >= MIN_SECRET_LENGTH)
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    secrets.sort();
+    secrets.dedup();
+
+    Ok(secrets)
+}
+
+fn mask_bytes(data: &[u8], secrets: &[String]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut index = 0;
+    while index < data.len() {
+        let matched_length = secrets
+            .iter()
+            .filter(|secret| data[index..].starts_with(secret.as_bytes()))
+            .map(|secret| secret.len())
+            .max();
+        match matched_length {
+            Some(length) => {
+                output.extend_from_slice(SECRET_MASK);
+                index += length;
+            }
+            None => {
+                output.push(data[index]);
+                index += 1;
+            }
+        }
+    }
+
+    output
+}
+
+/// Length of the data at the end which may be the start of a secret
+fn partial_secret_length(data: &[u8], secrets: &[String]) -> usize {
+    secrets
+        .iter()
+        .filter_map(|secret| {
+            (1..secret.len())
+                .rev()
+                .find(|length| data.ends_with(&secret.as_bytes()[..*length]))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Replace all secret values in the text
+pub(crate) fn mask_secrets(text: &str, secrets: &[String]) -> String {
+    String::from_utf8_lossy(&mask_bytes(text.as_bytes(), secrets)).to_string()
+}
+
+/// Writer masking all secrets, data is written as soon as it cannot be part of a secret (the
+/// rest is written on drop)
+#[cfg_attr(not(unix), allow(dead_code))]
+struct MaskingWriter<W: Write> {
+    writer: W,
+    secrets: Vec<String>,
+    pending: Vec<u8>,
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+impl<W: Write> MaskingWriter<W> {
+    fn new(writer: W, secrets: &[String]) -> MaskingWriter<W> {
+        MaskingWriter {
+            writer,
+            secrets: secrets.to_vec(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for MaskingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let masked = mask_bytes(&self.pending, &self.secrets);
+        let ready_length = masked.len() - partial_secret_length(&masked, &self.secrets);
+        self.writer.write_all(&masked[..ready_length])?;
+        self.writer.flush()?;
+        self.pending = masked[ready_length..].to_vec();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Drop for MaskingWriter<W> {
+    fn drop(&mut self) {
+        let _ = self
+            .writer
+            .write_all(&mask_bytes(&self.pending, &self.secrets));
+        let _ = self.writer.flush();
+    }
+}
+
+/// Duplicate of the current output stream, stays valid when the stream is redirected
+#[cfg(unix)]
+fn original_output(stream: OutputStream) -> io::Result<File> {
+    use std::os::fd::AsFd;
+
+    let fd = match stream {
+        OutputStream::Stdout => io::stdout().as_fd().try_clone_to_owned()?,
+        OutputStream::Stderr => io::stderr().as_fd().try_clone_to_owned()?,
+    };
+
+    Ok(File::from(fd))
+}
+
+/// Masks secrets in everything written to stdout and stderr while it is alive
+///
+/// Output of external commands is masked too, see [`OutputTee::replace`]. Only supported on
+/// unix, other platforms will print a warning and not mask any output.
+pub(crate) struct OutputMasking {
+    _tees: Vec<OutputTee>,
+}
+
+impl OutputMasking {
+    /// Start masking the secrets, will return None when there is nothing to mask
+    pub(crate) fn start(secrets: &[String]) -> NurResult<Option<OutputMasking>> {
+        if secrets.is_empty() {
+            return Ok(None);
+        }
+
+        #[cfg(unix)]
+        {
+            let mut tees = Vec::new();
+            for stream in [OutputStream::Stdout, OutputStream::Stderr] {
+                let writer = MaskingWriter::new(original_output(stream)?, secrets);
+                tees.push(OutputTee::replace(stream, Box::new(writer))?);
+            }
+
+            Ok(Some(OutputMasking { _tees: tees }))
+        }
+
+        #[cfg(not(unix))]
+        {
+            eprintln!(
+                "WARNING: masking secrets is only supported on unix, the task output may contain secret values"
+            );
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::init_engine_state;
+    use crate::names::{NUR_CONFIG_DIR, NUR_FILE};
+    use crate::state::NurState;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_mask_secrets() {
+        let secrets = vec![String::from("secret"), String::from("secret-token")];
+
+        assert_eq!(
+            mask_secrets("token=secret-token, other=secret.", &secrets),
+            "token=********, other=********."
+        );
+        assert_eq!(mask_secrets("nothing here", &secrets), "nothing here");
+    }
+
+    #[test]
+    fn test_masking_writer_handles_secrets_split_into_chunks() {
+        let secrets = vec![String::from("abab1234")];
+        let data = b"xabab1234 abab abab1234y";
+
+        for chunk_size in 1..data.len() {
+            let mut output = Vec::new();
+            {
+                let mut writer = MaskingWriter::new(&mut output, &secrets);
+                for chunk in data.chunks(chunk_size) {
+                    writer.write_all(chunk).unwrap();
+                }
+            }
+            assert_eq!(output, b"x******** abab ********y");
+        }
+    }
+
+    #[test]
+    fn test_secret_values_from_secrets_file_and_marked_env_vars() {
+        let temp_dir = tempdir().unwrap();
+        let temp_dir_path = temp_dir.path().to_path_buf();
+        fs::write(temp_dir.path().join(NUR_FILE), "").unwrap();
+        fs::create_dir(temp_dir.path().join(NUR_CONFIG_DIR)).unwrap();
+        fs::write(
+            temp_dir.path().join(".nur/secrets.nuon"),
+            "{API_TOKEN: \"token-value\", SHORT: \"abc\"}",
+        )
+        .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(
+                temp_dir.path().join(".nur/secrets.nuon"),
+                fs::Permissions::from_mode(0o600),
+            )
+            .unwrap();
+        }
+        fs::write(
+            temp_dir.path().join(".nur/env.nu"),
+            "$env.OTHER_SECRET = \"other-value\"\n$env.NUR_SECRETS = ($env.NUR_SECRETS | append OTHER_SECRET)",
+        )
+        .unwrap();
+
+        let nur_state = NurState::new(temp_dir_path.clone(), vec![String::from("nur")]).unwrap();
+        let engine_state = init_engine_state(temp_dir_path).unwrap();
+        let mut nur_engine = NurEngine::new(engine_state, nur_state).unwrap();
+        nur_engine.load_secrets().unwrap();
+        nur_engine.load_env().unwrap();
+
+        assert_eq!(
+            nur_engine
+                .eval_and_capture("$env.API_TOKEN")
+                .unwrap()
+                .to_string_lossy(),
+            "token-value"
+        );
+        assert_eq!(
+            secret_values(&mut nur_engine).unwrap(),
+            vec![String::from("other-value"), String::from("token-value")]
+        );
+    }
+
+    #[test]
+    fn test_decrypt_secrets_file() {
+        use age::secrecy::ExposeSecret;
+
+        let temp_dir = tempdir().unwrap();
+        let identity = age::x25519::Identity::generate();
+        let identity_path = temp_dir.path().join("identity.txt");
+        fs::write(&identity_path, identity.to_string().expose_secret()).unwrap();
+        let identity_path = Some(identity_path.to_string_lossy().to_string());
+        let secrets = "{API_TOKEN: \"token-value\"}";
+
+        let secrets_path = temp_dir.path().join("secrets.nuon.age");
+        fs::write(
+            &secrets_path,
+            age::encrypt(&identity.to_public(), secrets.as_bytes()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            decrypt_secrets_file(&secrets_path, identity_path.clone()).unwrap(),
+            secrets
+        );
+
+        let armored_secrets_path = temp_dir.path().join("armored.nuon.age");
+        fs::write(
+            &armored_secrets_path,
+            age::encrypt_and_armor(&identity.to_public(), secrets.as_bytes()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            decrypt_secrets_file(&armored_secrets_path, identity_path).unwrap(),
+            secrets
+        );
+    }
+
+    #[test]
+    fn test_decrypt_secrets_file_needs_matching_identity() {
+        use age::secrecy::ExposeSecret;
+
+        let temp_dir = tempdir().unwrap();
+        let other_identity = age::x25519::Identity::generate();
+        let identity_path = temp_dir.path().join("identity.txt");
+        fs::write(&identity_path, other_identity.to_string().expose_secret()).unwrap();
+        let secrets_path = temp_dir.path().join("secrets.nuon.age");
+        fs::write(
+            &secrets_path,
+            age::encrypt(
+                &age::x25519::Identity::generate().to_public(),
+                b"{API_TOKEN: \"token-value\"}",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            decrypt_secrets_file(&secrets_path, None),
+            Err(NurError::MissingSecretsIdentity(_))
+        ));
+        assert!(matches!(
+            decrypt_secrets_file(
+                &secrets_path,
+                Some(identity_path.to_string_lossy().to_string())
+            ),
+            Err(NurError::SecretsDecryptionError(_, _))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secrets_file_readable_by_others_is_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let secrets_path = temp_dir.path().join("secrets.nuon");
+        fs::write(&secrets_path, "{API_TOKEN: \"token-value\"}").unwrap();
+
+        fs::set_permissions(&secrets_path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(matches!(
+            check_secrets_file_permissions(&secrets_path),
+            Err(NurError::InsecureSecretsFile(_))
+        ));
+
+        fs::set_permissions(&secrets_path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(check_secrets_file_permissions(&secrets_path).is_ok());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::errors::{NurError, NurResult};
+use nu_cli::{eval_source, NuCompleter, NuHighlighter, NuValidator, NushellPrompt};
+use nu_cmd_base::hook::eval_hooks;
+use nu_engine::ClosureEvalOnce;
+use nu_protocol::engine::{EngineState, Stack};
+use nu_protocol::{report_shell_error, HistoryFileFormat, PipelineData, Value};
+use reedline::{
+    default_emacs_keybindings, ColumnarMenu, Emacs, FileBackedHistory, History, KeyCode,
+    KeyModifiers, MenuBuilder, Reedline, ReedlineEvent, ReedlineMenu, Signal, SqliteBackedHistory,
+};
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Open the shell history inside the given directory, using the history format of the config
+fn open_history(engine_state: &EngineState, history_dir: &Path) -> NurResult<Box<dyn History>> {
+    let history_config = engine_state.get_config().history;
+    let history_path = history_dir.join(history_config.file_format.default_file_name());
+
+    let history: Box<dyn History> = match history_config.file_format {
+        HistoryFileFormat::Plaintext => Box::new(
+            FileBackedHistory::with_file(history_config.max_size as usize, history_path)
+                .map_err(|err| NurError::IoError(err.to_string()))?,
+        ),
+        HistoryFileFormat::Sqlite => Box::new(
+            SqliteBackedHistory::with_file(history_path, None, None)
+                .map_err(|err| NurError::IoError(err.to_string()))?,
+        ),
+    };
+
+    Ok(history)
+}
+
+/// Get a prompt part from the environment, which may be a string or a closure
+fn prompt_string(engine_state: &EngineState, stack: &mut Stack, name: &str) -> Option<String> {
+    let output = match stack.get_env_var(engine_state, name)?.clone() {
+        Value::Closure { val, .. } => ClosureEvalOnce::new(engine_state, stack, *val)
+            .run_with_input(PipelineData::empty())
+            .map_err(|err| report_shell_error(engine_state, &err))
+            .ok()?,
+        value @ Value::String { .. } => PipelineData::Value(value, None),
+        _ => return None,
+    };
+
+    output
+        .collect_string("", engine_state.get_config())
+        .ok()
+        .map(|prompt| prompt.trim_end_matches(['\n', '\r']).to_string())
+}
+
+fn update_prompt(engine_state: &EngineState, stack: &mut Stack, nu_prompt: &mut NushellPrompt) {
+    nu_prompt.update_all_prompt_strings(
+        prompt_string(engine_state, stack, "PROMPT_COMMAND"),
+        prompt_string(engine_state, stack, "PROMPT_COMMAND_RIGHT"),
+        prompt_string(engine_state, stack, "PROMPT_INDICATOR"),
+        prompt_string(engine_state, stack, "PROMPT_MULTILINE_INDICATOR"),
+        (
+            prompt_string(engine_state, stack, "PROMPT_INDICATOR_VI_INSERT"),
+            prompt_string(engine_state, stack, "PROMPT_INDICATOR_VI_NORMAL"),
+        ),
+        engine_state.get_config().render_right_prompt_on_last_line,
+    );
+}
+
+fn create_line_editor(history: Box<dyn History>) -> Reedline {
+    let mut keybindings = default_emacs_keybindings();
+    keybindings.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::Tab,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::Menu("completion_menu".to_string()),
+            ReedlineEvent::MenuNext,
+        ]),
+    );
+
+    Reedline::create()
+        .with_history(history)
+        .with_history_exclusion_prefix(Some(" ".into()))
+        .with_menu(ReedlineMenu::EngineCompleter(Box::new(
+            ColumnarMenu::default().with_name("completion_menu"),
+        )))
+        .with_edit_mode(Box::new(Emacs::new(keybindings)))
+}
+
+/// Run the interactive shell, storing the history in the given directory
+///
+/// nu 0.101 always reads its history from the nu config dir, so the shell uses its own line
+/// editor (with the nu completer, highlighter and prompt) instead of `evaluate_repl`.
+pub(crate) fn run_shell(
+    engine_state: &mut EngineState,
+    mut stack: Stack,
+    history_dir: &Path,
+) -> NurResult<()> {
+    if !io::stdin().is_terminal() {
+        return Err(NurError::EnteredShellError());
+    }
+
+    let mut line_editor = create_line_editor(open_history(engine_state, history_dir)?);
+    let config = engine_state.get_config().clone();
+    let mut nu_prompt = NushellPrompt::new(
+        config.shell_integration.osc133,
+        config.shell_integration.osc633,
+        engine_state.clone(),
+        stack.clone(),
+    );
+
+    let mut entry_num = 0;
+    loop {
+        if let Err(err) = engine_state.merge_env(&mut stack) {
+            report_shell_error(engine_state, &err);
+        }
+        engine_state.reset_signals();
+
+        // Runs the reload of the nurfiles, see nur_shell.nu
+        let pre_prompt_hooks = engine_state.get_config().hooks.pre_prompt.clone();
+        if let Err(err) = eval_hooks(
+            engine_state,
+            &mut stack,
+            vec![],
+            &pre_prompt_hooks,
+            "pre_prompt",
+        ) {
+            report_shell_error(engine_state, &err);
+        }
+
+        // Completions and highlighting need to know about reloaded tasks and changed variables
+        let current_engine_state = Arc::new(engine_state.clone());
+        let current_stack = Arc::new(stack.clone());
+        line_editor = line_editor
+            .with_completer(Box::new(NuCompleter::new(
+                current_engine_state.clone(),
+                current_stack.clone(),
+            )))
+            .with_highlighter(Box::new(NuHighlighter {
+                engine_state: current_engine_state.clone(),
+                stack: current_stack,
+            }))
+            .with_validator(Box::new(NuValidator {
+                engine_state: current_engine_state,
+            }));
+        update_prompt(engine_state, &mut stack, &mut nu_prompt);
+
+        match line_editor.read_line(&nu_prompt) {
+            Ok(Signal::Success(line)) => {
+                let pre_execution_hooks = engine_state.get_config().hooks.pre_execution.clone();
+                if let Err(err) = eval_hooks(
+                    engine_state,
+                    &mut stack,
+                    vec![],
+                    &pre_execution_hooks,
+                    "pre_execution",
+                ) {
+                    report_shell_error(engine_state, &err);
+                }
+
+                // `exit` ends the process, so the history needs to be written right away
+                if engine_state.get_config().history.sync_on_enter {
+                    let _ = line_editor.sync_history();
+                }
+
+                entry_num += 1;
+                eval_source(
+                    engine_state,
+                    &mut stack,
+                    line.as_bytes(),
+                    &format!("entry #{entry_num}"),
+                    PipelineData::empty(),
+                    false,
+                );
+            }
+            Ok(Signal::CtrlC) => continue,
+            Ok(Signal::CtrlD) => break,
+            Err(_) => return Err(NurError::EnteredShellError()),
+        }
+    }
+
+    line_editor
+        .sync_history()
+        .map_err(|err| NurError::IoError(err.to_string()))?;
+
+    Ok(())
+}
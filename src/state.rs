@@ -1,14 +1,25 @@
-use crate::args::gather_commandline_args;
+use crate::args::{gather_commandline_args, NurArgs};
+use crate::ci::CiProvider;
 use crate::errors::NurResult;
+#[cfg(feature = "plugin")]
+use crate::names::NUR_CONFIG_PLUGIN_REGISTRY_FILENAME;
 use crate::names::{
-    NUR_CONFIG_CONFIG_FILENAME, NUR_CONFIG_DIR, NUR_CONFIG_ENV_FILENAME, NUR_CONFIG_LIB_PATH,
-    NUR_FILE, NUR_LOCAL_FILE,
+    NUR_CONFIG_CACHE_DIRNAME, NUR_CONFIG_CONFIG_FILENAME, NUR_CONFIG_DIR,
+    NUR_CONFIG_ENCRYPTED_SECRETS_FILENAME, NUR_CONFIG_ENV_FILENAME, NUR_CONFIG_HISTORY_FILENAME,
+    NUR_CONFIG_LIB_PATH, NUR_CONFIG_LOGS_DIRNAME, NUR_CONFIG_PLUGINS_FILENAME,
+    NUR_CONFIG_SECRETS_FILENAME, NUR_CONFIG_SHELL_DIRNAME, NUR_CONFIG_TASK_CACHE_FILENAME,
+    NUR_FILE, NUR_LOCAL_FILE, NU_CONFIG_CONFIG_FILENAME, NU_CONFIG_ENV_FILENAME,
 };
 use crate::path::find_project_path;
-use std::path::PathBuf;
+use nu_parser::escape_for_script_arg;
+use std::path::{Path, PathBuf};
 
+/// Paths and the parsed command line of a nur project
+///
+/// The state is set up by [`NurState::new`] and only changed by nur itself, embedding code may
+/// read it using the accessors below.
 #[derive(Clone)]
-pub(crate) struct NurState {
+pub struct NurState {
     pub(crate) run_path: PathBuf,
     pub(crate) has_project_path: bool,
     pub(crate) project_path: PathBuf,
@@ -17,14 +28,46 @@ pub(crate) struct NurState {
     pub(crate) lib_dir_path: PathBuf,
     pub(crate) env_path: PathBuf,
     pub(crate) config_path: PathBuf,
+    pub(crate) history_path: PathBuf,
+    pub(crate) secrets_path: PathBuf,
+    pub(crate) encrypted_secrets_path: PathBuf,
+    pub(crate) logs_dir: PathBuf,
+    pub(crate) task_cache_path: PathBuf,
+    pub(crate) plugins_path: PathBuf,
+    #[cfg(feature = "plugin")]
+    pub(crate) plugin_registry_path: PathBuf,
+    pub(crate) shell_config_dir: PathBuf,
+
+    // nu's env.nu and config.nu, only loaded with --use-nu-config
+    pub(crate) nu_env_path: Option<PathBuf>,
+    pub(crate) nu_config_path: Option<PathBuf>,
 
     pub(crate) nurfile_path: PathBuf,
     pub(crate) local_nurfile_path: PathBuf,
 
     pub(crate) args_to_nur: Vec<String>,
     pub(crate) has_task_call: bool,
-    pub(crate) task_call: Vec<String>,
+    pub(crate) task_call: Vec<String>, // escaped to be used as nu code
+    pub(crate) raw_task_call: Vec<String>, // as passed on the command line
     pub(crate) task_name: Option<String>, // full task name, like "nur some-task"
+    pub(crate) nur_args: Option<NurArgs>,
+
+    pub(crate) ci_provider: Option<CiProvider>,
+}
+
+/// Escape the task arguments, the "nur" prefix and the task name are never escaped
+fn escape_task_call(raw_task_call: &[String]) -> Vec<String> {
+    raw_task_call
+        .iter()
+        .enumerate()
+        .map(|(index, arg)| {
+            if index < 2 {
+                arg.clone()
+            } else {
+                escape_for_script_arg(arg)
+            }
+        })
+        .collect()
 }
 
 impl NurState {
@@ -39,13 +82,35 @@ impl NurState {
         let lib_dir_path = config_dir.join(NUR_CONFIG_LIB_PATH);
         let env_path = config_dir.join(NUR_CONFIG_ENV_FILENAME);
         let config_path = config_dir.join(NUR_CONFIG_CONFIG_FILENAME);
+        let history_path = config_dir.join(NUR_CONFIG_HISTORY_FILENAME);
+        let secrets_path = config_dir.join(NUR_CONFIG_SECRETS_FILENAME);
+        let encrypted_secrets_path = config_dir.join(NUR_CONFIG_ENCRYPTED_SECRETS_FILENAME);
+        let logs_dir = config_dir.join(NUR_CONFIG_LOGS_DIRNAME);
+        let task_cache_path = config_dir
+            .join(NUR_CONFIG_CACHE_DIRNAME)
+            .join(NUR_CONFIG_TASK_CACHE_FILENAME);
+        let plugins_path = config_dir.join(NUR_CONFIG_PLUGINS_FILENAME);
+        #[cfg(feature = "plugin")]
+        let plugin_registry_path = config_dir
+            .join(NUR_CONFIG_CACHE_DIRNAME)
+            .join(NUR_CONFIG_PLUGIN_REGISTRY_FILENAME);
+        let shell_config_dir = config_dir.join(NUR_CONFIG_SHELL_DIRNAME);
+
+        // Set nu config files
+        let nu_config_dir = nu_path::nu_config_dir().map(PathBuf::from);
+        let nu_env_path = nu_config_dir
+            .as_ref()
+            .map(|dir| dir.join(NU_CONFIG_ENV_FILENAME));
+        let nu_config_path = nu_config_dir
+            .as_ref()
+            .map(|dir| dir.join(NU_CONFIG_CONFIG_FILENAME));
 
         // Set nurfiles
         let nurfile_path = project_path.join(NUR_FILE);
         let local_nurfile_path = project_path.join(NUR_LOCAL_FILE);
 
         // Parse args into bits
-        let (args_to_nur, has_task_call, task_call) = gather_commandline_args(args)?;
+        let (args_to_nur, has_task_call, raw_task_call) = gather_commandline_args(args)?;
 
         Ok(NurState {
             run_path,
@@ -56,19 +121,116 @@ impl NurState {
             lib_dir_path,
             env_path,
             config_path,
+            history_path,
+            secrets_path,
+            encrypted_secrets_path,
+            logs_dir,
+            task_cache_path,
+            plugins_path,
+            #[cfg(feature = "plugin")]
+            plugin_registry_path,
+            shell_config_dir,
+
+            nu_env_path,
+            nu_config_path,
 
             nurfile_path,
             local_nurfile_path,
 
             args_to_nur,
             has_task_call,
-            task_call,
+            task_call: escape_task_call(&raw_task_call),
+            raw_task_call,
             task_name: None,
+            nur_args: None,
+
+            ci_provider: None,
         })
     }
+
+    /// Replace the task call, given unescaped like on the command line (including "nur")
+    pub(crate) fn set_task_call(&mut self, raw_task_call: Vec<String>) {
+        self.task_call = escape_task_call(&raw_task_call);
+        self.raw_task_call = raw_task_call;
+    }
+
+    /// Unescaped arguments passed to the task, empty if no task was found
+    pub fn task_args(&self) -> &[String] {
+        match &self.task_name {
+            Some(task_name) => self
+                .raw_task_call
+                .get(task_name.split(' ').count()..)
+                .unwrap_or_default(),
+            None => &[],
+        }
+    }
+
+    /// Directory nur was started in
+    pub fn run_path(&self) -> &Path {
+        &self.run_path
+    }
+
+    /// Whether a nurfile was found in the run path or its parents
+    pub fn has_project_path(&self) -> bool {
+        self.has_project_path
+    }
+
+    /// Directory of the nurfile, the run path if no nurfile was found
+    pub fn project_path(&self) -> &Path {
+        &self.project_path
+    }
+
+    /// The .nur directory of the project
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    pub fn lib_dir_path(&self) -> &Path {
+        &self.lib_dir_path
+    }
+
+    pub fn env_path(&self) -> &Path {
+        &self.env_path
+    }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    pub fn history_path(&self) -> &Path {
+        &self.history_path
+    }
+
+    pub fn logs_dir(&self) -> &Path {
+        &self.logs_dir
+    }
+
+    pub fn nurfile_path(&self) -> &Path {
+        &self.nurfile_path
+    }
+
+    pub fn local_nurfile_path(&self) -> &Path {
+        &self.local_nurfile_path
+    }
+
+    /// Whether nur was called with a task (or with arguments which are not nur flags)
+    pub fn has_task_call(&self) -> bool {
+        self.has_task_call
+    }
+
+    /// The task call escaped to be used as nu code, starting with "nur"
+    pub fn task_call(&self) -> &[String] {
+        &self.task_call
+    }
+
+    /// Full name of the called task (like "nur some-task"), set after the nurfiles were loaded
+    pub fn task_name(&self) -> Option<&str> {
+        self.task_name.as_deref()
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
     use std::fs::File;
@@ -99,6 +261,24 @@ mod tests {
         assert_eq!(state.lib_dir_path, temp_dir_path.join(".nur/scripts"));
         assert_eq!(state.env_path, temp_dir_path.join(".nur/env.nu"));
         assert_eq!(state.config_path, temp_dir_path.join(".nur/config.nu"));
+        assert_eq!(state.history_path, temp_dir_path.join(".nur/history.jsonl"));
+        assert_eq!(state.secrets_path, temp_dir_path.join(".nur/secrets.nuon"));
+        assert_eq!(
+            state.encrypted_secrets_path,
+            temp_dir_path.join(".nur/secrets.nuon.age")
+        );
+        assert_eq!(state.logs_dir, temp_dir_path.join(".nur/logs"));
+        assert_eq!(
+            state.task_cache_path,
+            temp_dir_path.join(".nur/cache/tasks.json")
+        );
+        assert_eq!(state.plugins_path, temp_dir_path.join(".nur/plugins"));
+        #[cfg(feature = "plugin")]
+        assert_eq!(
+            state.plugin_registry_path,
+            temp_dir_path.join(".nur/cache/plugin.msgpackz")
+        );
+        assert_eq!(state.shell_config_dir, temp_dir_path.join(".nur/shell"));
 
         assert_eq!(state.nurfile_path, temp_dir_path.join("nurfile"));
         assert_eq!(
@@ -0,0 +1,20 @@
+use crate::errors::{NurError, NurResult};
+use crate::task_metadata::TaskMetadata;
+use std::path::{Path, PathBuf};
+
+/// Get the full path of the directory declared by the task, which needs to exist
+pub(crate) fn resolve_task_dir(
+    project_path: &Path,
+    task_name: &str,
+    extra_description: &str,
+) -> NurResult<Option<PathBuf>> {
+    let Some(dir) = TaskMetadata::parse(extra_description).dir else {
+        return Ok(None);
+    };
+    let task_dir = project_path.join(&dir);
+    if !task_dir.is_dir() {
+        return Err(NurError::TaskDirNotFound(String::from(task_name), dir));
+    }
+
+    Ok(Some(task_dir))
+}
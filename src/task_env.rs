@@ -0,0 +1,36 @@
+use crate::errors::{NurError, NurResult};
+use crate::task_metadata::TaskMetadata;
+use nu_protocol::engine::{EngineState, Stack};
+use nu_protocol::{Span, Value};
+
+/// Ensure all env vars declared by the task are set, missing ones will use their default
+pub(crate) fn apply_task_env(
+    engine_state: &mut EngineState,
+    stack: &Stack,
+    task_name: &str,
+    extra_description: &str,
+) -> NurResult<()> {
+    let env_vars = TaskMetadata::parse(extra_description).env_vars;
+
+    let mut missing = Vec::new();
+    for env_var in env_vars {
+        if stack.get_env_var(engine_state, &env_var.name).is_some() {
+            continue;
+        }
+        match env_var.default {
+            Some(default) => {
+                engine_state.add_env_var(env_var.name, Value::string(default, Span::unknown()))
+            }
+            None => missing.push(env_var.name),
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(NurError::MissingTaskEnv(
+            String::from(task_name),
+            missing.join(", "),
+        ));
+    }
+
+    Ok(())
+}
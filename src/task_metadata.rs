@@ -0,0 +1,255 @@
+use nu_ansi_term::Color;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{Example, PipelineData, ShellError, Signature};
+
+// Doc comment lines starting with this declare an env var, like "env: NAME=default Description"
+const TASK_ENV_PREFIX: &str = "env:";
+// Doc comment line declaring the directory a task runs in, like "dir: frontend"
+const TASK_DIR_PREFIX: &str = "dir:";
+// Doc comment line marking a task to require confirmation, may include a reason like
+// "confirm: This will drop all data"
+const CONFIRM_MARKER: &str = "confirm";
+
+/// Environment variable declared by a task
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TaskEnvVar {
+    pub name: String,
+    pub default: Option<String>,
+    pub description: String,
+}
+
+/// Metadata declared in the extra description of a task (the doc comment after the first
+/// paragraph), see [`TaskMetadata::parse`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TaskMetadata {
+    pub env_vars: Vec<TaskEnvVar>,
+    pub dir: Option<String>,
+    // The reason may be empty
+    pub confirmation_reason: Option<String>,
+    // Extra description with all metadata lines removed
+    pub description: String,
+}
+
+fn is_env_var_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_env_line(line: &str) -> Option<TaskEnvVar> {
+    let declaration = line.strip_prefix(TASK_ENV_PREFIX)?.trim();
+    let (variable, description) = declaration
+        .split_once(char::is_whitespace)
+        .unwrap_or((declaration, ""));
+    let (name, default) = match variable.split_once('=') {
+        Some((name, default)) => (name, Some(String::from(default))),
+        None => (variable, None),
+    };
+    if !is_env_var_name(name) {
+        return None;
+    }
+
+    Some(TaskEnvVar {
+        name: String::from(name),
+        default,
+        description: String::from(description.trim()),
+    })
+}
+
+fn parse_dir_line(line: &str) -> Option<String> {
+    let dir = line.strip_prefix(TASK_DIR_PREFIX)?.trim();
+    if dir.is_empty() {
+        None
+    } else {
+        Some(String::from(dir))
+    }
+}
+
+fn parse_confirm_line(line: &str) -> Option<String> {
+    let rest = line.strip_prefix(CONFIRM_MARKER)?;
+    if rest.is_empty() {
+        Some(String::new())
+    } else {
+        rest.strip_prefix(':')
+            .map(|reason| String::from(reason.trim()))
+    }
+}
+
+impl TaskMetadata {
+    /// Parse the lines declaring env vars ("env: NAME=default Description"), the task directory
+    /// ("dir: frontend") and the confirmation ("confirm" or "confirm: reason")
+    ///
+    /// Only the first "dir:" and "confirm" lines are used, all metadata lines are removed from
+    /// the description.
+    pub(crate) fn parse(extra_description: &str) -> TaskMetadata {
+        let mut metadata = TaskMetadata::default();
+        let mut remaining_lines = Vec::new();
+        for line in extra_description.lines() {
+            let trimmed_line = line.trim();
+            if let Some(env_var) = parse_env_line(trimmed_line) {
+                metadata.env_vars.push(env_var);
+            } else if let Some(dir) = parse_dir_line(trimmed_line) {
+                metadata.dir.get_or_insert(dir);
+            } else if let Some(reason) = parse_confirm_line(trimmed_line) {
+                metadata.confirmation_reason.get_or_insert(reason);
+            } else {
+                remaining_lines.push(line);
+            }
+        }
+        metadata.description = remaining_lines.join("\n").trim().to_string();
+
+        metadata
+    }
+
+    fn is_empty(&self) -> bool {
+        self.env_vars.is_empty() && self.dir.is_none() && self.confirmation_reason.is_none()
+    }
+
+    /// Sections for the help output, showing the metadata instead of the raw lines
+    fn help_sections(&self) -> Vec<String> {
+        let mut sections = Vec::new();
+        if !self.env_vars.is_empty() {
+            let mut section = format!("{}:", Color::Green.paint("Environment"));
+            for env_var in &self.env_vars {
+                let requirement = match &env_var.default {
+                    Some(default) => format!("(default: {})", default),
+                    None => String::from("(required)"),
+                };
+                section.push_str(&format!(
+                    "\n  {} {}",
+                    Color::Cyan.paint(&env_var.name),
+                    requirement
+                ));
+                if !env_var.description.is_empty() {
+                    section.push_str(&format!(": {}", env_var.description));
+                }
+            }
+            sections.push(section);
+        }
+        if let Some(dir) = &self.dir {
+            sections.push(format!("{}: {}", Color::Green.paint("Directory"), dir));
+        }
+        if let Some(reason) = &self.confirmation_reason {
+            let mut section = format!("{}: required", Color::Green.paint("Confirmation"));
+            if !reason.is_empty() {
+                section.push_str(&format!(" ({})", reason));
+            }
+            sections.push(section);
+        }
+
+        sections
+    }
+}
+
+/// Wraps a task to show its metadata as separate sections in the help output
+#[derive(Clone)]
+pub(crate) struct TaskHelp {
+    command: Box<dyn Command>,
+    extra_description: String,
+}
+
+impl TaskHelp {
+    /// Wrap the task if it declares any metadata
+    pub(crate) fn new(command: &dyn Command) -> Option<TaskHelp> {
+        let metadata = TaskMetadata::parse(command.extra_description());
+        if metadata.is_empty() {
+            return None;
+        }
+
+        let mut parts = metadata.help_sections();
+        if !metadata.description.is_empty() {
+            parts.insert(0, metadata.description);
+        }
+
+        Some(TaskHelp {
+            command: command.clone_box(),
+            extra_description: parts.join("\n\n"),
+        })
+    }
+}
+
+impl Command for TaskHelp {
+    fn name(&self) -> &str {
+        self.command.name()
+    }
+
+    fn signature(&self) -> Signature {
+        self.command.signature()
+    }
+
+    fn description(&self) -> &str {
+        self.command.description()
+    }
+
+    fn extra_description(&self) -> &str {
+        &self.extra_description
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        self.command.examples()
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        self.command.run(engine_state, stack, call, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_task_metadata() {
+        let metadata = TaskMetadata::parse(
+            "Some more text\nenv: API_TOKEN The token to use\n  env: REGION=eu-west-1\n\
+            dir: frontend/app\nconfirm: This will drop all data\nenv: not-valid\nMore text",
+        );
+
+        assert_eq!(
+            metadata,
+            TaskMetadata {
+                env_vars: vec![
+                    TaskEnvVar {
+                        name: String::from("API_TOKEN"),
+                        default: None,
+                        description: String::from("The token to use"),
+                    },
+                    TaskEnvVar {
+                        name: String::from("REGION"),
+                        default: Some(String::from("eu-west-1")),
+                        description: String::from(""),
+                    },
+                ],
+                dir: Some(String::from("frontend/app")),
+                confirmation_reason: Some(String::from("This will drop all data")),
+                description: String::from("Some more text\nenv: not-valid\nMore text"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_task_metadata_confirm_without_reason() {
+        let metadata = TaskMetadata::parse("Some text\nconfirm\ndir: first\ndir: second");
+
+        assert_eq!(metadata.confirmation_reason, Some(String::new()));
+        assert_eq!(metadata.dir, Some(String::from("first")));
+        assert_eq!(metadata.description, "Some text");
+    }
+
+    #[test]
+    fn test_parse_task_metadata_without_declarations() {
+        let metadata = TaskMetadata::parse(
+            "Just some text\n\nenvironment: nothing\nconfirmed something\ndirectory: x\ndir:",
+        );
+
+        assert!(metadata.is_empty());
+        assert_eq!(
+            metadata.description,
+            "Just some text\n\nenvironment: nothing\nconfirmed something\ndirectory: x\ndir:"
+        );
+    }
+}